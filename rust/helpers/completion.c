@@ -0,0 +1,16 @@
+// SPDX-License-Identifier: GPL-2.0
+
+#include <linux/completion.h>
+#include <linux/export.h>
+
+void rust_helper___init_completion(struct completion *x, const char *name,
+				   struct lock_class_key *key)
+{
+	x->done = 0;
+	__init_swait_queue_head(&x->wait, name, key);
+}
+
+void rust_helper_reinit_completion(struct completion *x)
+{
+	reinit_completion(x);
+}
@@ -0,0 +1,14 @@
+// SPDX-License-Identifier: GPL-2.0
+
+#include <linux/cpumask.h>
+#include <linux/export.h>
+
+unsigned int rust_helper_cpumask_next(int n, const struct cpumask *srcp)
+{
+	return cpumask_next(n, srcp);
+}
+
+bool rust_helper_cpu_possible(unsigned int cpu)
+{
+	return cpu_possible(cpu);
+}
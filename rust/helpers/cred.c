@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: GPL-2.0
+
+#include <linux/cred.h>
+#include <linux/export.h>
+#include <linux/uidgid.h>
+
+const struct cred *rust_helper_get_cred(const struct cred *cred)
+{
+	return get_cred(cred);
+}
+
+void rust_helper_put_cred(const struct cred *cred)
+{
+	put_cred(cred);
+}
+
+const struct cred *rust_helper_get_current_cred(void)
+{
+	return get_current_cred();
+}
+
+#ifndef CONFIG_USER_NS
+kuid_t rust_helper_make_kuid(struct user_namespace *from, uid_t uid)
+{
+	return make_kuid(from, uid);
+}
+
+kgid_t rust_helper_make_kgid(struct user_namespace *from, gid_t gid)
+{
+	return make_kgid(from, gid);
+}
+
+uid_t rust_helper_from_kuid(struct user_namespace *to, kuid_t kuid)
+{
+	return from_kuid(to, kuid);
+}
+
+gid_t rust_helper_from_kgid(struct user_namespace *to, kgid_t kgid)
+{
+	return from_kgid(to, kgid);
+}
+#endif
@@ -7,20 +7,36 @@
  * Sorted alphabetically.
  */
 
+#include "atomic.c"
 #include "blk.c"
 #include "bug.c"
 #include "build_assert.c"
 #include "build_bug.c"
+#include "completion.c"
+#include "cpumask.c"
+#include "cred.c"
+#include "delay.c"
 #include "err.c"
+#include "hrtimer.c"
 #include "kunit.c"
+#include "mm.c"
 #include "mutex.c"
 #include "page.c"
+#include "percpu.c"
+#include "pid.c"
+#include "poll.c"
 #include "rbtree.c"
+#include "rcu.c"
 #include "refcount.c"
+#include "rwlock.c"
+#include "seqlock.c"
 #include "signal.c"
 #include "slab.c"
 #include "spinlock.c"
+#include "sync.c"
 #include "task.c"
 #include "uaccess.c"
+#include "vmalloc.c"
 #include "wait.c"
 #include "workqueue.c"
+#include "xarray.c"
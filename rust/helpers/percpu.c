@@ -0,0 +1,26 @@
+// SPDX-License-Identifier: GPL-2.0
+
+#include <linux/cpumask.h>
+#include <linux/export.h>
+#include <linux/percpu.h>
+#include <linux/smp.h>
+
+void *rust_helper_per_cpu_ptr(void __percpu *ptr, unsigned int cpu)
+{
+	return per_cpu_ptr(ptr, cpu);
+}
+
+int rust_helper_get_cpu(void)
+{
+	return get_cpu();
+}
+
+void rust_helper_put_cpu(void)
+{
+	put_cpu();
+}
+
+unsigned int rust_helper_nr_cpu_ids(void)
+{
+	return nr_cpu_ids;
+}
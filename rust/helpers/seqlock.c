@@ -0,0 +1,82 @@
+// SPDX-License-Identifier: GPL-2.0
+
+#include <linux/export.h>
+#include <linux/seqlock.h>
+
+void rust_helper___seqlock_init(seqlock_t *sl, const char *name,
+				struct lock_class_key *key)
+{
+	seqlock_init(sl);
+#ifdef CONFIG_DEBUG_SPINLOCK
+	__raw_spin_lock_init(spinlock_check(&sl->lock), name, key,
+			     LD_WAIT_CONFIG);
+#endif
+}
+
+void rust_helper_write_seqlock(seqlock_t *sl)
+{
+	write_seqlock(sl);
+}
+
+void rust_helper_write_sequnlock(seqlock_t *sl)
+{
+	write_sequnlock(sl);
+}
+
+/* Same as write_seqlock(), but fails instead of spinning on contention. */
+int rust_helper_write_tryseqlock(seqlock_t *sl)
+{
+	if (!spin_trylock(&sl->lock))
+		return 0;
+
+	do_write_seqcount_begin(&sl->seqcount.seqcount);
+	return 1;
+}
+
+void rust_helper_seqlock_assert_is_held(seqlock_t *sl)
+{
+	lockdep_assert_held(&sl->lock);
+}
+
+unsigned rust_helper_read_seqbegin(const seqlock_t *sl)
+{
+	return read_seqbegin(sl);
+}
+
+int rust_helper_read_seqretry(const seqlock_t *sl, unsigned start)
+{
+	return read_seqretry(sl, start);
+}
+
+void rust_helper___seqcount_init(seqcount_t *s, const char *name,
+				 struct lock_class_key *key)
+{
+	__seqcount_init(s, name, key);
+}
+
+/*
+ * Writers of a plain `seqcount_t` must not be preempted, but the lock that
+ * serialises them may be a sleeping one, so disable preemption here like the
+ * `seqcount_LOCKNAME_t` variants do.
+ */
+void rust_helper_write_seqcount_begin(seqcount_t *s)
+{
+	preempt_disable();
+	write_seqcount_begin(s);
+}
+
+void rust_helper_write_seqcount_end(seqcount_t *s)
+{
+	write_seqcount_end(s);
+	preempt_enable();
+}
+
+unsigned rust_helper_read_seqcount_begin(const seqcount_t *s)
+{
+	return read_seqcount_begin(s);
+}
+
+int rust_helper_read_seqcount_retry(const seqcount_t *s, unsigned start)
+{
+	return read_seqcount_retry(s, start);
+}
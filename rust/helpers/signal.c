@@ -7,3 +7,8 @@ int rust_helper_signal_pending(struct task_struct *t)
 {
 	return signal_pending(t);
 }
+
+int rust_helper_fatal_signal_pending(struct task_struct *t)
+{
+	return fatal_signal_pending(t);
+}
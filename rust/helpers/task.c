@@ -17,3 +17,22 @@ void rust_helper_put_task_struct(struct task_struct *t)
 {
 	put_task_struct(t);
 }
+
+void rust_helper_might_resched(void)
+{
+	might_resched();
+}
+
+void rust_helper_set_current_state(unsigned int state)
+{
+	set_current_state(state);
+#ifdef CONFIG_DEBUG_ATOMIC_SLEEP
+	/* Blame the Rust caller rather than this helper for the state change. */
+	current->task_state_change = _RET_IP_;
+#endif
+}
+
+void rust_helper___set_current_state(unsigned int state)
+{
+	__set_current_state(state);
+}
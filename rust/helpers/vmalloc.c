@@ -0,0 +1,14 @@
+// SPDX-License-Identifier: GPL-2.0
+
+#include <linux/export.h>
+#include <linux/pgtable.h>
+
+pgprot_t rust_helper_PAGE_KERNEL(void)
+{
+	return PAGE_KERNEL;
+}
+
+pgprot_t rust_helper_pgprot_noncached(pgprot_t prot)
+{
+	return pgprot_noncached(prot);
+}
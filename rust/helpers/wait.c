@@ -7,3 +7,8 @@ void rust_helper_init_wait(struct wait_queue_entry *wq_entry)
 {
 	init_wait(wq_entry);
 }
+
+void rust_helper_wake_up_pollfree(struct wait_queue_head *wq_head)
+{
+	wake_up_pollfree(wq_head);
+}
@@ -13,3 +13,21 @@ void rust_helper_init_work_with_key(struct work_struct *work, work_func_t func,
 	INIT_LIST_HEAD(&work->entry);
 	work->func = func;
 }
+
+void rust_helper_init_delayed_work_with_key(struct delayed_work *dwork,
+					    work_func_t func, const char *name,
+					    struct lock_class_key *work_key,
+					    struct lock_class_key *timer_key)
+{
+	rust_helper_init_work_with_key(&dwork->work, func, false, name,
+				       work_key);
+	init_timer_key(&dwork->timer, delayed_work_timer_fn, TIMER_IRQSAFE,
+		       name, timer_key);
+}
+
+struct workqueue_struct *rust_helper_alloc_workqueue(const char *name,
+						     unsigned int flags,
+						     int max_active)
+{
+	return alloc_workqueue("%s", flags, max_active, name);
+}
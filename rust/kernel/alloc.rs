@@ -6,8 +6,15 @@
 #[cfg(not(testlib))]
 mod allocator;
 pub mod box_ext;
+#[cfg(CONFIG_KUNIT)]
+#[cfg(not(test))]
+#[cfg(not(testlib))]
+pub mod fault_inject;
+pub mod scope;
 pub mod vec_ext;
 
+use crate::error::{code::EINVAL, Result};
+
 /// Indicates an allocation error.
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub struct AllocError;
@@ -48,6 +55,33 @@ impl core::ops::Not for Flags {
     }
 }
 
+/// A NUMA node to allocate memory from.
+///
+/// Allocations made with [`NumaNode::NO_NODE`] have no node preference, which is what the
+/// allocation functions that do not take a node use.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct NumaNode(i32);
+
+impl NumaNode {
+    /// No node preference (`NUMA_NO_NODE`).
+    pub const NO_NODE: NumaNode = NumaNode(bindings::NUMA_NO_NODE);
+
+    /// Creates a new NUMA node identifier.
+    ///
+    /// Returns [`EINVAL`] if `node` is not lower than `MAX_NUMNODES`.
+    pub fn new(node: i32) -> Result<Self> {
+        if node < 0 || node as u32 >= bindings::MAX_NUMNODES {
+            return Err(EINVAL);
+        }
+        Ok(Self(node))
+    }
+
+    /// Get the raw representation of this node.
+    pub(crate) fn as_raw(self) -> i32 {
+        self.0
+    }
+}
+
 /// Allocation flags.
 ///
 /// These are meant to be used in functions that can allocate memory.
@@ -85,4 +119,41 @@ pub mod flags {
     /// use any filesystem callback.  It is very likely to fail to allocate memory, even for very
     /// small allocations.
     pub const GFP_NOWAIT: Flags = Flags(bindings::GFP_NOWAIT);
+
+    /// Uses direct reclaim to discard clean pages or slab pages that do not require the starting
+    /// of any physical IO.
+    ///
+    /// Prefer the scoped [`memalloc_noio_save`] API instead, which also covers allocations made by
+    /// callees that are not aware of the IO restriction.
+    ///
+    /// [`memalloc_noio_save`]: super::scope::memalloc_noio_save
+    pub const GFP_NOIO: Flags = Flags(bindings::GFP_NOIO);
+
+    /// Uses direct reclaim but will not use any filesystem interfaces.
+    ///
+    /// Prefer the scoped [`memalloc_nofs_save`] API instead, which also covers allocations made by
+    /// callees that are not aware of the filesystem restriction.
+    ///
+    /// [`memalloc_nofs_save`]: super::scope::memalloc_nofs_save
+    pub const GFP_NOFS: Flags = Flags(bindings::GFP_NOFS);
+
+    /// Suppresses allocation failure reports.
+    ///
+    /// This is normally or'd with other flags.
+    pub const __GFP_NOWARN: Flags = Flags(bindings::__GFP_NOWARN);
+
+    /// The allocator will retry if there is some progress in reclaiming memory, but will
+    /// eventually fail instead of triggering the OOM killer.
+    ///
+    /// This is normally or'd with other flags.
+    pub const __GFP_RETRY_MAYFAIL: Flags = Flags(bindings::__GFP_RETRY_MAYFAIL);
+
+    /// The allocator must retry infinitely, the caller cannot handle allocation failures.
+    ///
+    /// New users should be evaluated carefully, and it must not be used for costly (high order)
+    /// allocations. Using this flag for an allocation that may fail is a bug regardless of whether
+    /// the error path is handled.
+    ///
+    /// This is normally or'd with other flags.
+    pub const __GFP_NOFAIL: Flags = Flags(bindings::__GFP_NOFAIL);
 }
@@ -2,7 +2,7 @@
 
 //! Allocator support.
 
-use super::{flags::*, Flags};
+use super::{flags::*, Flags, NumaNode};
 use core::alloc::{GlobalAlloc, Layout};
 use core::ptr;
 
@@ -23,12 +23,53 @@ pub(crate) unsafe fn krealloc_aligned(ptr: *mut u8, new_layout: Layout, flags: F
     // object (see comments in `kmalloc()` for more information).
     let size = layout.size();
 
+    #[cfg(CONFIG_KUNIT)]
+    if super::fault_inject::should_fail(flags) {
+        return ptr::null_mut();
+    }
+
     // SAFETY:
     // - `ptr` is either null or a pointer returned from a previous `k{re}alloc()` by the
     //   function safety requirement.
     // - `size` is greater than 0 since it's from `layout.size()` (which cannot be zero according
     //   to the function safety requirement)
-    unsafe { bindings::krealloc(ptr as *const core::ffi::c_void, size, flags.0) as *mut u8 }
+    let new_ptr =
+        unsafe { bindings::krealloc(ptr as *const core::ffi::c_void, size, flags.0) as *mut u8 };
+
+    #[cfg(CONFIG_KUNIT)]
+    if ptr.is_null() && !new_ptr.is_null() {
+        super::fault_inject::record_alloc();
+    }
+
+    new_ptr
+}
+
+/// Calls `kmalloc_node` with a proper size to alloc a new object aligned to `layout`'s alignment
+/// on the given NUMA node.
+///
+/// # Safety
+///
+/// `layout` must have a non-zero size.
+pub(crate) unsafe fn kmalloc_node_aligned(layout: Layout, flags: Flags, node: NumaNode) -> *mut u8 {
+    // Customized layouts from `Layout::from_size_align()` can have size < align, so pad first. See
+    // `krealloc_aligned` for why this results in a properly aligned object.
+    let size = layout.pad_to_align().size();
+
+    #[cfg(CONFIG_KUNIT)]
+    if super::fault_inject::should_fail(flags) {
+        return ptr::null_mut();
+    }
+
+    // SAFETY: `size` is greater than 0 since it's from `layout.size()` (which cannot be zero
+    // according to the function safety requirement).
+    let new_ptr = unsafe { bindings::kmalloc_node(size, flags.0, node.as_raw()) as *mut u8 };
+
+    #[cfg(CONFIG_KUNIT)]
+    if !new_ptr.is_null() {
+        super::fault_inject::record_alloc();
+    }
+
+    new_ptr
 }
 
 unsafe impl GlobalAlloc for KernelAllocator {
@@ -39,6 +80,9 @@ unsafe impl GlobalAlloc for KernelAllocator {
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
+        #[cfg(CONFIG_KUNIT)]
+        super::fault_inject::record_free();
+
         unsafe {
             bindings::kfree(ptr as *const core::ffi::c_void);
         }
@@ -2,7 +2,7 @@
 
 //! Extensions to [`Box`] for fallible allocations.
 
-use super::{AllocError, Flags};
+use super::{AllocError, Flags, NumaNode};
 use alloc::boxed::Box;
 use core::{mem::MaybeUninit, ptr, result::Result};
 
@@ -18,6 +18,29 @@ pub trait BoxExt<T>: Sized {
     /// The allocation may fail, in which case an error is returned.
     fn new_uninit(flags: Flags) -> Result<Box<MaybeUninit<T>>, AllocError>;
 
+    /// Allocates a new box on the given NUMA node.
+    ///
+    /// The allocation may fail, in which case an error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kernel::alloc::{box_ext::BoxExt, NumaNode};
+    ///
+    /// let value = Box::new_on_node([0u8; 64], GFP_KERNEL, NumaNode::new(0)?)?;
+    /// assert_eq!(*value, [0; 64]);
+    ///
+    /// let value = Box::new_on_node(42, GFP_KERNEL, NumaNode::NO_NODE)?;
+    /// assert_eq!(*value, 42);
+    /// # Ok::<(), Error>(())
+    /// ```
+    fn new_on_node(x: T, flags: Flags, node: NumaNode) -> Result<Self, AllocError>;
+
+    /// Allocates a new uninitialised box on the given NUMA node.
+    ///
+    /// The allocation may fail, in which case an error is returned.
+    fn new_uninit_on_node(flags: Flags, node: NumaNode) -> Result<Box<MaybeUninit<T>>, AllocError>;
+
     /// Drops the contents, but keeps the allocation.
     ///
     /// # Examples
@@ -45,11 +68,26 @@ impl<T> BoxExt<T> for Box<T> {
         Ok(unsafe { b.assume_init() })
     }
 
+    fn new_on_node(x: T, flags: Flags, node: NumaNode) -> Result<Self, AllocError> {
+        let mut b = <Self as BoxExt<_>>::new_uninit_on_node(flags, node)?;
+        b.write(x);
+        // SAFETY: We just wrote to it.
+        Ok(unsafe { b.assume_init() })
+    }
+
     #[cfg(any(test, testlib))]
     fn new_uninit(_flags: Flags) -> Result<Box<MaybeUninit<T>>, AllocError> {
         Ok(Box::new_uninit())
     }
 
+    #[cfg(any(test, testlib))]
+    fn new_uninit_on_node(
+        _flags: Flags,
+        _node: NumaNode,
+    ) -> Result<Box<MaybeUninit<T>>, AllocError> {
+        Ok(Box::new_uninit())
+    }
+
     #[cfg(not(any(test, testlib)))]
     fn new_uninit(flags: Flags) -> Result<Box<MaybeUninit<T>>, AllocError> {
         let ptr = if core::mem::size_of::<MaybeUninit<T>>() == 0 {
@@ -74,6 +112,29 @@ impl<T> BoxExt<T> for Box<T> {
         Ok(unsafe { Box::from_raw(ptr) })
     }
 
+    #[cfg(not(any(test, testlib)))]
+    fn new_uninit_on_node(flags: Flags, node: NumaNode) -> Result<Box<MaybeUninit<T>>, AllocError> {
+        let ptr = if core::mem::size_of::<MaybeUninit<T>>() == 0 {
+            core::ptr::NonNull::<_>::dangling().as_ptr()
+        } else {
+            let layout = core::alloc::Layout::new::<MaybeUninit<T>>();
+
+            // SAFETY: The only source of safety issues is sleeping on atomic context, which is
+            // addressed by klint. Lastly, the type is not a SZT (checked above).
+            let ptr = unsafe { super::allocator::kmalloc_node_aligned(layout, flags, node) };
+            if ptr.is_null() {
+                return Err(AllocError);
+            }
+
+            ptr.cast::<MaybeUninit<T>>()
+        };
+
+        // SAFETY: For non-zero-sized types, we allocate above using the same allocator as the
+        // global one, as `kfree` also frees objects allocated with `kmalloc_node`. For zero-sized
+        // types, we use `NonNull::dangling`.
+        Ok(unsafe { Box::from_raw(ptr) })
+    }
+
     fn drop_contents(this: Self) -> Box<MaybeUninit<T>> {
         let ptr = Box::into_raw(this);
         // SAFETY: `ptr` is valid, because it came from `Box::into_raw`.
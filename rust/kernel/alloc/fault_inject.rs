@@ -0,0 +1,197 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Allocation fault injection and accounting for KUnit tests.
+//!
+//! Error paths of functions that take [`Flags`] (e.g., [`Arc::new`], [`BoxExt::new`],
+//! [`VecExt::push`] or [`RBTreeNode::new`]) are rarely exercised since allocations seldom fail.
+//! This module allows a KUnit test to make the Nth allocation performed by the `kernel` crate on
+//! its behalf fail, and keeps per-test allocation counters so that leaks can be asserted.
+//!
+//! Only allocations made from the task running the test that owns the [`FaultInjection`] instance
+//! are considered; allocations made by other tasks (including other tests) are neither counted
+//! nor failed.
+//!
+//! [`Flags`]: super::Flags
+//! [`Arc::new`]: crate::sync::Arc::new
+//! [`BoxExt::new`]: super::box_ext::BoxExt::new
+//! [`VecExt::push`]: super::vec_ext::VecExt::push
+//! [`RBTreeNode::new`]: crate::rbtree::RBTreeNode::new
+
+use super::Flags;
+use crate::{bindings, error::code::*, error::Result};
+use core::{
+    marker::PhantomData,
+    ptr,
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
+};
+
+/// The KUnit test that currently owns the fault injection state, or null if there is none.
+static OWNER: AtomicPtr<bindings::kunit> = AtomicPtr::new(ptr::null_mut());
+
+/// The number of allocation attempts left until one is failed; zero means disarmed.
+static COUNTDOWN: AtomicUsize = AtomicUsize::new(0);
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+static FREES: AtomicUsize = AtomicUsize::new(0);
+static FAILURES: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns whether the current task is running the test that owns the fault injection state.
+#[inline]
+fn current_is_owner() -> bool {
+    let owner = OWNER.load(Ordering::Acquire);
+    if owner.is_null() {
+        return false;
+    }
+
+    // SAFETY: FFI call without safety requirements.
+    ptr::eq(owner, unsafe { bindings::kunit_get_current_test() })
+}
+
+/// Called by the allocator before each allocation attempt, returns whether it must fail.
+///
+/// Allocations made with [`__GFP_NOFAIL`] are never failed, since their callers rely on them
+/// succeeding. They do not count as attempts either.
+///
+/// [`__GFP_NOFAIL`]: super::flags::__GFP_NOFAIL
+#[inline]
+pub(crate) fn should_fail(flags: Flags) -> bool {
+    if flags.as_raw() & bindings::__GFP_NOFAIL != 0 || !current_is_owner() {
+        return false;
+    }
+
+    let prev = COUNTDOWN.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+    if prev == Ok(1) {
+        FAILURES.fetch_add(1, Ordering::Relaxed);
+        true
+    } else {
+        false
+    }
+}
+
+/// Called by the allocator when a new object has been allocated.
+#[inline]
+pub(crate) fn record_alloc() {
+    if current_is_owner() {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Called by the allocator when an object is freed.
+#[inline]
+pub(crate) fn record_free() {
+    if current_is_owner() {
+        FREES.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A snapshot of the allocation counters of a [`FaultInjection`] instance.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct AllocStats {
+    /// The number of objects allocated.
+    pub allocs: usize,
+    /// The number of objects freed.
+    pub frees: usize,
+    /// The number of allocation attempts that were failed on purpose.
+    pub failures: usize,
+}
+
+impl AllocStats {
+    /// Returns the number of objects that were allocated but not freed yet.
+    ///
+    /// Saturates at zero if objects allocated before the [`FaultInjection`] instance was created
+    /// are freed while it is active.
+    pub fn live(&self) -> usize {
+        self.allocs.saturating_sub(self.frees)
+    }
+}
+
+/// Allocation fault injection and accounting for the current KUnit test.
+///
+/// Only one instance may exist at a time. While it exists, allocations made by the `kernel` crate
+/// from the task running the test that created it are counted, and [`FaultInjection::fail_nth`]
+/// can be used to make one of them fail. Dropping it disarms the fault injection.
+///
+/// # Examples
+///
+/// ```
+/// use kernel::alloc::fault_inject::FaultInjection;
+/// use kernel::sync::Arc;
+///
+/// let inject = FaultInjection::new()?;
+///
+/// // Fail the second allocation from now on.
+/// inject.fail_nth(2);
+///
+/// let a = Arc::new(10, GFP_KERNEL);
+/// assert!(a.is_ok());
+/// assert!(Arc::new(20, GFP_KERNEL).is_err());
+///
+/// // Only one allocation is failed, so the next one succeeds.
+/// let mut v = Vec::new();
+/// v.push(30, GFP_KERNEL)?;
+///
+/// let stats = inject.stats();
+/// assert_eq!(stats.failures, 1);
+/// assert_eq!(stats.live(), 2);
+///
+/// drop(a);
+/// drop(v);
+/// assert_eq!(inject.stats().live(), 0);
+/// # Ok::<(), Error>(())
+/// ```
+pub struct FaultInjection {
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl FaultInjection {
+    /// Claims the fault injection state for the current KUnit test and resets its counters.
+    ///
+    /// Returns [`EINVAL`] if the current task is not running a KUnit test, and [`EBUSY`] if
+    /// another [`FaultInjection`] instance already exists.
+    pub fn new() -> Result<Self> {
+        // SAFETY: FFI call without safety requirements.
+        let test = unsafe { bindings::kunit_get_current_test() };
+        if test.is_null() {
+            return Err(EINVAL);
+        }
+
+        OWNER
+            .compare_exchange(ptr::null_mut(), test, Ordering::AcqRel, Ordering::Relaxed)
+            .map_err(|_| EBUSY)?;
+
+        COUNTDOWN.store(0, Ordering::Relaxed);
+        ALLOCS.store(0, Ordering::Relaxed);
+        FREES.store(0, Ordering::Relaxed);
+        FAILURES.store(0, Ordering::Relaxed);
+
+        Ok(Self {
+            _not_send: PhantomData,
+        })
+    }
+
+    /// Makes the `n`th allocation attempt from now on fail, counting from one.
+    ///
+    /// Only a single allocation is failed; passing zero disarms a previously requested failure.
+    /// Allocations made with [`__GFP_NOFAIL`] are not counted, and never fail.
+    ///
+    /// [`__GFP_NOFAIL`]: super::flags::__GFP_NOFAIL
+    pub fn fail_nth(&self, n: usize) {
+        COUNTDOWN.store(n, Ordering::Relaxed);
+    }
+
+    /// Returns the allocation counters accumulated since this instance was created.
+    pub fn stats(&self) -> AllocStats {
+        AllocStats {
+            allocs: ALLOCS.load(Ordering::Relaxed),
+            frees: FREES.load(Ordering::Relaxed),
+            failures: FAILURES.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Drop for FaultInjection {
+    fn drop(&mut self) {
+        COUNTDOWN.store(0, Ordering::Relaxed);
+        OWNER.store(ptr::null_mut(), Ordering::Release);
+    }
+}
@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Scoped allocation constraints.
+//!
+//! The scoped API restricts the allocations made by the current task, including those made by
+//! callees that are not aware of the restriction (e.g., C code allocating with [`GFP_KERNEL`]).
+//! This is the preferred way to avoid recursing into the IO or filesystem layers during memory
+//! reclaim, for example, from storage code that must not deadlock on itself.
+//!
+//! Scopes nest, and must be left in the reverse order in which they were entered. Since the
+//! guards are not [`Send`], they cannot leave the task that created them.
+//!
+//! C header: [`include/linux/sched/mm.h`](srctree/include/linux/sched/mm.h)
+//!
+//! [`GFP_KERNEL`]: super::flags::GFP_KERNEL
+
+use crate::bindings;
+use core::{ffi::c_uint, marker::PhantomData};
+
+/// A guard for a scope in which allocations of the current task implicitly drop `__GFP_IO` and
+/// `__GFP_FS`.
+///
+/// It is created by [`memalloc_noio_save`] and leaves the scope when dropped.
+#[must_use = "the scope ends immediately when the guard is unused"]
+pub struct NoIoScope {
+    flags: c_uint,
+    _not_send: PhantomData<*mut ()>,
+}
+
+/// Enters a scope in which allocations of the current task implicitly drop `__GFP_IO` and
+/// `__GFP_FS`, as if they were made with [`GFP_NOIO`].
+///
+/// # Examples
+///
+/// ```
+/// use kernel::alloc::scope::memalloc_noio_save;
+///
+/// let scope = memalloc_noio_save();
+/// // This allocation will not start any IO to reclaim memory.
+/// let b = Box::new(42, GFP_KERNEL)?;
+/// drop(scope);
+/// # Ok::<(), Error>(())
+/// ```
+///
+/// [`GFP_NOIO`]: super::flags::GFP_NOIO
+pub fn memalloc_noio_save() -> NoIoScope {
+    NoIoScope {
+        // SAFETY: FFI call without safety requirements.
+        flags: unsafe { bindings::memalloc_noio_save() },
+        _not_send: PhantomData,
+    }
+}
+
+impl Drop for NoIoScope {
+    fn drop(&mut self) {
+        // SAFETY: `self.flags` was returned by `memalloc_noio_save` on this task, since the guard
+        // is not `Send`.
+        unsafe { bindings::memalloc_noio_restore(self.flags) };
+    }
+}
+
+/// A guard for a scope in which allocations of the current task implicitly drop `__GFP_FS`.
+///
+/// It is created by [`memalloc_nofs_save`] and leaves the scope when dropped.
+#[must_use = "the scope ends immediately when the guard is unused"]
+pub struct NoFsScope {
+    flags: c_uint,
+    _not_send: PhantomData<*mut ()>,
+}
+
+/// Enters a scope in which allocations of the current task implicitly drop `__GFP_FS`, as if they
+/// were made with [`GFP_NOFS`].
+///
+/// # Examples
+///
+/// ```
+/// use kernel::alloc::scope::memalloc_nofs_save;
+///
+/// let _scope = memalloc_nofs_save();
+/// // This allocation will not call back into filesystems to reclaim memory.
+/// let mut v = Vec::new();
+/// v.push(42, GFP_KERNEL)?;
+/// # Ok::<(), Error>(())
+/// ```
+///
+/// [`GFP_NOFS`]: super::flags::GFP_NOFS
+pub fn memalloc_nofs_save() -> NoFsScope {
+    NoFsScope {
+        // SAFETY: FFI call without safety requirements.
+        flags: unsafe { bindings::memalloc_nofs_save() },
+        _not_send: PhantomData,
+    }
+}
+
+impl Drop for NoFsScope {
+    fn drop(&mut self) {
+        // SAFETY: `self.flags` was returned by `memalloc_nofs_save` on this task, since the guard
+        // is not `Send`.
+        unsafe { bindings::memalloc_nofs_restore(self.flags) };
+    }
+}
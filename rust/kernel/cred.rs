@@ -0,0 +1,402 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Credentials, user and group IDs, and capabilities.
+//!
+//! C headers: [`include/linux/cred.h`](srctree/include/linux/cred.h),
+//! [`include/linux/uidgid.h`](srctree/include/linux/uidgid.h) and
+//! [`include/linux/capability.h`](srctree/include/linux/capability.h).
+
+use crate::{
+    bindings,
+    types::{ARef, AlwaysRefCounted, Opaque},
+};
+use core::ptr;
+
+/// Wraps the kernel's `struct cred`.
+///
+/// Credentials are used for various security checks in the kernel. Most fields of credentials
+/// never change once initialised, which is why they can be shared through a reference count.
+///
+/// Instances of this type are always refcounted, that is, a call to `get_cred` ensures that the
+/// allocation remains valid at least until the matching call to `put_cred`.
+///
+/// # Invariants
+///
+/// Instances of this type are always refcounted.
+///
+/// # Examples
+///
+/// The following example shows the permission check of an ioctl that may only be used by the
+/// owner of an object, or by an administrator:
+///
+/// ```
+/// use kernel::cred::{capable, Capability, Credential, Kuid, UserNamespace};
+///
+/// fn check_owner(owner: Kuid) -> Result {
+///     let cred = Credential::current();
+///     if cred.euid() == owner || capable(Capability::SysAdmin) {
+///         Ok(())
+///     } else {
+///         Err(EPERM)
+///     }
+/// }
+///
+/// let ns = UserNamespace::init();
+/// let root = Kuid::from_uid(ns, 0).ok_or(EINVAL)?;
+/// assert_eq!(root.to_uid(ns), Some(0));
+///
+/// // Tests run in a kernel thread, with the credentials of the root user.
+/// check_owner(root)?;
+/// # Ok::<(), Error>(())
+/// ```
+#[repr(transparent)]
+pub struct Credential(Opaque<bindings::cred>);
+
+// SAFETY: By design, the only way to access a `Credential` is via an immutable reference or an
+// `ARef`. This means that the only situation in which a `Credential` can be accessed mutably is
+// when the refcount drops to zero and the destructor runs. It is safe for that to happen on any
+// thread, so it is ok for this type to be `Send`.
+unsafe impl Send for Credential {}
+
+// SAFETY: It's OK to access `Credential` through shared references from other threads because
+// the fields that it gives access to never change once the credentials are published.
+unsafe impl Sync for Credential {}
+
+impl Credential {
+    /// Creates a reference to a [`Credential`] from a valid pointer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `ptr` is valid and remains valid for the lifetime of the
+    /// returned [`Credential`] reference.
+    pub unsafe fn from_ptr<'a>(ptr: *const bindings::cred) -> &'a Credential {
+        // SAFETY: The safety requirements guarantee the validity of the dereference, while the
+        // `Credential` type being transparent makes the cast ok.
+        unsafe { &*ptr.cast() }
+    }
+
+    /// Returns the subjective credentials of the current task (`get_current_cred`).
+    ///
+    /// These are the credentials used when the task acts upon an object, so they are the ones to
+    /// use for permission checks. They differ from the objective credentials returned by
+    /// [`Task::cred`] while they are overridden with `override_creds`.
+    ///
+    /// [`Task::cred`]: crate::task::Task::cred
+    pub fn current() -> ARef<Self> {
+        // SAFETY: FFI call without safety requirements. `get_current_cred` returns a new reference
+        // to valid credentials.
+        let ptr = unsafe { bindings::get_current_cred() };
+
+        // SAFETY: `ptr` is valid and non-null, and we own the reference that was taken above.
+        unsafe { ARef::from_raw(ptr::NonNull::new_unchecked(ptr.cast_mut().cast())) }
+    }
+
+    /// Returns the real user ID.
+    pub fn uid(&self) -> Kuid {
+        // SAFETY: By the type invariant, we know that `self.0` is valid.
+        Kuid(unsafe { *ptr::addr_of!((*self.0.get()).uid) })
+    }
+
+    /// Returns the effective user ID, which is used for most permission checks.
+    pub fn euid(&self) -> Kuid {
+        // SAFETY: By the type invariant, we know that `self.0` is valid.
+        Kuid(unsafe { *ptr::addr_of!((*self.0.get()).euid) })
+    }
+
+    /// Returns the user ID used for file system accesses.
+    pub fn fsuid(&self) -> Kuid {
+        // SAFETY: By the type invariant, we know that `self.0` is valid.
+        Kuid(unsafe { *ptr::addr_of!((*self.0.get()).fsuid) })
+    }
+
+    /// Returns the real group ID.
+    pub fn gid(&self) -> Kgid {
+        // SAFETY: By the type invariant, we know that `self.0` is valid.
+        Kgid(unsafe { *ptr::addr_of!((*self.0.get()).gid) })
+    }
+
+    /// Returns the effective group ID, which is used for most permission checks.
+    pub fn egid(&self) -> Kgid {
+        // SAFETY: By the type invariant, we know that `self.0` is valid.
+        Kgid(unsafe { *ptr::addr_of!((*self.0.get()).egid) })
+    }
+
+    /// Returns the group ID used for file system accesses.
+    pub fn fsgid(&self) -> Kgid {
+        // SAFETY: By the type invariant, we know that `self.0` is valid.
+        Kgid(unsafe { *ptr::addr_of!((*self.0.get()).fsgid) })
+    }
+
+    /// Returns the user namespace that the credentials belong to.
+    ///
+    /// Capabilities of the credentials apply to the objects of this namespace.
+    pub fn user_ns(&self) -> &UserNamespace {
+        // SAFETY: By the type invariant, we know that `self.0` is valid. The credentials hold a
+        // reference to their user namespace, so it lives at least as long as them.
+        unsafe { UserNamespace::from_ptr(*ptr::addr_of!((*self.0.get()).user_ns)) }
+    }
+}
+
+// SAFETY: The type invariants guarantee that `Credential` is always refcounted.
+unsafe impl AlwaysRefCounted for Credential {
+    fn inc_ref(&self) {
+        // SAFETY: The existence of a shared reference means that the refcount is nonzero.
+        unsafe { bindings::get_cred(self.0.get()) };
+    }
+
+    unsafe fn dec_ref(obj: ptr::NonNull<Credential>) {
+        // SAFETY: The safety requirements guarantee that the refcount is nonzero. The cast is okay
+        // because `Credential` has the same representation as `struct cred`.
+        unsafe { bindings::put_cred(obj.cast().as_ptr()) };
+    }
+}
+
+/// Wraps the kernel's `struct user_namespace`.
+///
+/// User namespaces map the user and group IDs seen by userspace to the kernel-internal [`Kuid`]
+/// and [`Kgid`].
+///
+/// # Invariants
+///
+/// The namespace is valid for the lifetime of the reference.
+#[repr(transparent)]
+pub struct UserNamespace(Opaque<bindings::user_namespace>);
+
+// SAFETY: User namespaces can be used from any thread, and their mappings are only read through
+// shared references.
+unsafe impl Sync for UserNamespace {}
+
+impl UserNamespace {
+    /// Creates a reference to a [`UserNamespace`] from a valid pointer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `ptr` is valid and remains valid for the lifetime of the
+    /// returned [`UserNamespace`] reference.
+    pub unsafe fn from_ptr<'a>(ptr: *const bindings::user_namespace) -> &'a UserNamespace {
+        // SAFETY: The safety requirements guarantee the validity of the dereference, while the
+        // `UserNamespace` type being transparent makes the cast ok.
+        unsafe { &*ptr.cast() }
+    }
+
+    /// Returns the initial user namespace (`init_user_ns`), in which IDs are mapped to themselves.
+    pub fn init() -> &'static UserNamespace {
+        // SAFETY: `init_user_ns` is a C global, which is always valid.
+        unsafe { Self::from_ptr(ptr::addr_of!(bindings::init_user_ns)) }
+    }
+
+    fn as_ptr(&self) -> *mut bindings::user_namespace {
+        self.0.get()
+    }
+}
+
+/// A kernel-internal user ID (`kuid_t`).
+///
+/// Unlike the user IDs seen by userspace, it does not depend on a user namespace. Use
+/// [`Kuid::from_uid`] and [`Kuid::to_uid`] to convert to and from the IDs of a given namespace.
+#[repr(transparent)]
+#[derive(Clone, Copy)]
+pub struct Kuid(bindings::kuid_t);
+
+impl Kuid {
+    /// Returns the user ID of the root user of the initial user namespace (`GLOBAL_ROOT_UID`).
+    pub fn global_root() -> Self {
+        Self(bindings::kuid_t { val: 0 })
+    }
+
+    /// Maps the user ID `uid` of `ns` (`make_kuid`).
+    ///
+    /// Returns [`None`] if `uid` has no mapping in `ns`.
+    pub fn from_uid(ns: &UserNamespace, uid: bindings::uid_t) -> Option<Self> {
+        // SAFETY: By the type invariant, `ns` is valid.
+        let kuid = unsafe { bindings::make_kuid(ns.as_ptr(), uid) };
+        // `INVALID_UID` is `-1`.
+        (kuid.val != bindings::uid_t::MAX).then_some(Self(kuid))
+    }
+
+    /// Returns the user ID that `self` is mapped to in `ns` (`from_kuid`).
+    ///
+    /// Returns [`None`] if `self` has no mapping in `ns`.
+    pub fn to_uid(self, ns: &UserNamespace) -> Option<bindings::uid_t> {
+        // SAFETY: By the type invariant, `ns` is valid.
+        let uid = unsafe { bindings::from_kuid(ns.as_ptr(), self.0) };
+        (uid != bindings::uid_t::MAX).then_some(uid)
+    }
+
+    /// Returns the raw value, which is the user ID in the initial user namespace.
+    pub fn as_raw(self) -> bindings::uid_t {
+        self.0.val
+    }
+}
+
+impl PartialEq for Kuid {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.val == other.0.val
+    }
+}
+
+impl Eq for Kuid {}
+
+/// A kernel-internal group ID (`kgid_t`).
+///
+/// Unlike the group IDs seen by userspace, it does not depend on a user namespace. Use
+/// [`Kgid::from_gid`] and [`Kgid::to_gid`] to convert to and from the IDs of a given namespace.
+#[repr(transparent)]
+#[derive(Clone, Copy)]
+pub struct Kgid(bindings::kgid_t);
+
+impl Kgid {
+    /// Returns the group ID of the root group of the initial user namespace (`GLOBAL_ROOT_GID`).
+    pub fn global_root() -> Self {
+        Self(bindings::kgid_t { val: 0 })
+    }
+
+    /// Maps the group ID `gid` of `ns` (`make_kgid`).
+    ///
+    /// Returns [`None`] if `gid` has no mapping in `ns`.
+    pub fn from_gid(ns: &UserNamespace, gid: bindings::gid_t) -> Option<Self> {
+        // SAFETY: By the type invariant, `ns` is valid.
+        let kgid = unsafe { bindings::make_kgid(ns.as_ptr(), gid) };
+        // `INVALID_GID` is `-1`.
+        (kgid.val != bindings::gid_t::MAX).then_some(Self(kgid))
+    }
+
+    /// Returns the group ID that `self` is mapped to in `ns` (`from_kgid`).
+    ///
+    /// Returns [`None`] if `self` has no mapping in `ns`.
+    pub fn to_gid(self, ns: &UserNamespace) -> Option<bindings::gid_t> {
+        // SAFETY: By the type invariant, `ns` is valid.
+        let gid = unsafe { bindings::from_kgid(ns.as_ptr(), self.0) };
+        (gid != bindings::gid_t::MAX).then_some(gid)
+    }
+
+    /// Returns the raw value, which is the group ID in the initial user namespace.
+    pub fn as_raw(self) -> bindings::gid_t {
+        self.0.val
+    }
+}
+
+impl PartialEq for Kgid {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.val == other.0.val
+    }
+}
+
+impl Eq for Kgid {}
+
+/// A capability, which grants a privilege to override some of the kernel's permission checks.
+///
+/// See `capabilities(7)` for the meaning of each of them.
+#[repr(u32)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Capability {
+    /// `CAP_CHOWN`.
+    Chown = bindings::CAP_CHOWN,
+    /// `CAP_DAC_OVERRIDE`.
+    DacOverride = bindings::CAP_DAC_OVERRIDE,
+    /// `CAP_DAC_READ_SEARCH`.
+    DacReadSearch = bindings::CAP_DAC_READ_SEARCH,
+    /// `CAP_FOWNER`.
+    Fowner = bindings::CAP_FOWNER,
+    /// `CAP_FSETID`.
+    Fsetid = bindings::CAP_FSETID,
+    /// `CAP_KILL`.
+    Kill = bindings::CAP_KILL,
+    /// `CAP_SETGID`.
+    Setgid = bindings::CAP_SETGID,
+    /// `CAP_SETUID`.
+    Setuid = bindings::CAP_SETUID,
+    /// `CAP_SETPCAP`.
+    Setpcap = bindings::CAP_SETPCAP,
+    /// `CAP_LINUX_IMMUTABLE`.
+    LinuxImmutable = bindings::CAP_LINUX_IMMUTABLE,
+    /// `CAP_NET_BIND_SERVICE`.
+    NetBindService = bindings::CAP_NET_BIND_SERVICE,
+    /// `CAP_NET_BROADCAST`.
+    NetBroadcast = bindings::CAP_NET_BROADCAST,
+    /// `CAP_NET_ADMIN`.
+    NetAdmin = bindings::CAP_NET_ADMIN,
+    /// `CAP_NET_RAW`.
+    NetRaw = bindings::CAP_NET_RAW,
+    /// `CAP_IPC_LOCK`.
+    IpcLock = bindings::CAP_IPC_LOCK,
+    /// `CAP_IPC_OWNER`.
+    IpcOwner = bindings::CAP_IPC_OWNER,
+    /// `CAP_SYS_MODULE`.
+    SysModule = bindings::CAP_SYS_MODULE,
+    /// `CAP_SYS_RAWIO`.
+    SysRawio = bindings::CAP_SYS_RAWIO,
+    /// `CAP_SYS_CHROOT`.
+    SysChroot = bindings::CAP_SYS_CHROOT,
+    /// `CAP_SYS_PTRACE`.
+    SysPtrace = bindings::CAP_SYS_PTRACE,
+    /// `CAP_SYS_PACCT`.
+    SysPacct = bindings::CAP_SYS_PACCT,
+    /// `CAP_SYS_ADMIN`.
+    SysAdmin = bindings::CAP_SYS_ADMIN,
+    /// `CAP_SYS_BOOT`.
+    SysBoot = bindings::CAP_SYS_BOOT,
+    /// `CAP_SYS_NICE`.
+    SysNice = bindings::CAP_SYS_NICE,
+    /// `CAP_SYS_RESOURCE`.
+    SysResource = bindings::CAP_SYS_RESOURCE,
+    /// `CAP_SYS_TIME`.
+    SysTime = bindings::CAP_SYS_TIME,
+    /// `CAP_SYS_TTY_CONFIG`.
+    SysTtyConfig = bindings::CAP_SYS_TTY_CONFIG,
+    /// `CAP_MKNOD`.
+    Mknod = bindings::CAP_MKNOD,
+    /// `CAP_LEASE`.
+    Lease = bindings::CAP_LEASE,
+    /// `CAP_AUDIT_WRITE`.
+    AuditWrite = bindings::CAP_AUDIT_WRITE,
+    /// `CAP_AUDIT_CONTROL`.
+    AuditControl = bindings::CAP_AUDIT_CONTROL,
+    /// `CAP_SETFCAP`.
+    Setfcap = bindings::CAP_SETFCAP,
+    /// `CAP_MAC_OVERRIDE`.
+    MacOverride = bindings::CAP_MAC_OVERRIDE,
+    /// `CAP_MAC_ADMIN`.
+    MacAdmin = bindings::CAP_MAC_ADMIN,
+    /// `CAP_SYSLOG`.
+    Syslog = bindings::CAP_SYSLOG,
+    /// `CAP_WAKE_ALARM`.
+    WakeAlarm = bindings::CAP_WAKE_ALARM,
+    /// `CAP_BLOCK_SUSPEND`.
+    BlockSuspend = bindings::CAP_BLOCK_SUSPEND,
+    /// `CAP_AUDIT_READ`.
+    AuditRead = bindings::CAP_AUDIT_READ,
+    /// `CAP_PERFMON`.
+    Perfmon = bindings::CAP_PERFMON,
+    /// `CAP_BPF`.
+    Bpf = bindings::CAP_BPF,
+    /// `CAP_CHECKPOINT_RESTORE`.
+    CheckpointRestore = bindings::CAP_CHECKPOINT_RESTORE,
+}
+
+impl Capability {
+    fn as_raw(self) -> core::ffi::c_int {
+        self as core::ffi::c_int
+    }
+}
+
+/// Returns whether the current task has the capability `cap` in the initial user namespace
+/// (`capable`).
+///
+/// This is the check to use for privileges that affect the whole system. If the check succeeds,
+/// the task is marked as having used a privilege (`PF_SUPERPRIV`).
+pub fn capable(cap: Capability) -> bool {
+    // SAFETY: `capable` is always safe to call from task context.
+    unsafe { bindings::capable(cap.as_raw()) }
+}
+
+/// Returns whether the current task has the capability `cap` in the user namespace `ns`
+/// (`ns_capable`).
+///
+/// This is the check to use for privileges over objects that belong to `ns`, such as the
+/// [`Credential::user_ns`] of their owner.
+pub fn ns_capable(ns: &UserNamespace, cap: Capability) -> bool {
+    // SAFETY: By the type invariant, `ns` is valid. `ns_capable` is always safe to call from task
+    // context.
+    unsafe { bindings::ns_capable(ns.as_ptr(), cap.as_raw()) }
+}
@@ -30,6 +30,7 @@ pub mod alloc;
 #[cfg(CONFIG_BLOCK)]
 pub mod block;
 mod build_assert;
+pub mod cred;
 pub mod device;
 pub mod error;
 #[cfg(CONFIG_RUST_FW_LOADER_ABSTRACTIONS)]
@@ -42,6 +43,8 @@ pub mod list;
 #[cfg(CONFIG_NET)]
 pub mod net;
 pub mod page;
+pub mod percpu;
+pub mod pid;
 pub mod prelude;
 pub mod print;
 pub mod rbtree;
@@ -55,7 +58,9 @@ pub mod task;
 pub mod time;
 pub mod types;
 pub mod uaccess;
+pub mod vmap;
 pub mod workqueue;
+pub mod xarray;
 
 #[doc(hidden)]
 pub use bindings;
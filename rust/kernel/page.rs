@@ -7,9 +7,13 @@ use crate::{
     bindings,
     error::code::*,
     error::Result,
-    uaccess::UserSliceReader,
+    init::PinInit,
+    new_mutex, pin_init,
+    sync::{lock::mutex::MutexBackend, lock::Guard, Mutex},
+    uaccess::{UserSliceReader, UserSliceWriter},
 };
 use core::ptr::{self, NonNull};
+use macros::pin_data;
 
 /// A bitwise shift for the page size.
 pub const PAGE_SHIFT: usize = bindings::PAGE_SHIFT as usize;
@@ -25,6 +29,10 @@ pub const PAGE_MASK: usize = !(PAGE_SIZE - 1);
 /// # Invariants
 ///
 /// The pointer is valid, and has ownership over the page.
+///
+/// The type is transparent over `*mut bindings::page`, so that slices of pages can be passed to C
+/// functions that take arrays of page pointers.
+#[repr(transparent)]
 pub struct Page {
     page: NonNull<bindings::page>,
 }
@@ -240,6 +248,129 @@ impl Page {
             reader.read_raw(unsafe { core::slice::from_raw_parts_mut(dst.cast(), len) })
         })
     }
+
+    /// Runs a piece of code with the contents of this page mapped as a byte slice.
+    ///
+    /// The page is unmapped when this call returns. The contents of a page that was allocated
+    /// without [`__GFP_ZERO`] are arbitrary.
+    ///
+    /// Users of the unsafe `*_raw` methods that write to the page are responsible for not racing
+    /// with this method; other writers need `&mut self`, which cannot coexist with `&self`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kernel::page::{Page, PAGE_SIZE};
+    ///
+    /// let mut page = Page::alloc_page(GFP_KERNEL | __GFP_ZERO)?;
+    /// page.with_slice_mut(|data| data[..4].copy_from_slice(b"rust"));
+    ///
+    /// page.with_slice(|data| {
+    ///     assert_eq!(data.len(), PAGE_SIZE);
+    ///     assert_eq!(&data[..4], b"rust");
+    ///     assert!(data[4..].iter().all(|b| *b == 0));
+    /// });
+    /// # Ok::<(), Error>(())
+    /// ```
+    ///
+    /// [`__GFP_ZERO`]: crate::alloc::flags::__GFP_ZERO
+    pub fn with_slice<T>(&self, f: impl FnOnce(&[u8]) -> T) -> T {
+        self.with_page_mapped(|addr| {
+            // SAFETY: `addr` is valid for `PAGE_SIZE` bytes while the closure runs. Writers to the
+            // page either hold `&mut self` (which cannot coexist with `&self`) or use the `*_raw`
+            // methods, whose callers guarantee that they do not race with this read.
+            f(unsafe { core::slice::from_raw_parts(addr, PAGE_SIZE) })
+        })
+    }
+
+    /// Runs a piece of code with the contents of this page mapped as a mutable byte slice.
+    ///
+    /// The page is unmapped when this call returns.
+    pub fn with_slice_mut<T>(&mut self, f: impl FnOnce(&mut [u8]) -> T) -> T {
+        self.with_page_mapped(|addr| {
+            // SAFETY: `addr` is valid for `PAGE_SIZE` bytes while the closure runs, and `&mut self`
+            // guarantees that nothing else accesses the page concurrently.
+            f(unsafe { core::slice::from_raw_parts_mut(addr, PAGE_SIZE) })
+        })
+    }
+
+    /// Copies data from userspace into this page.
+    ///
+    /// This method will perform bounds checks on the page offset. If `offset .. offset+len` goes
+    /// outside of the page, then this call returns [`EINVAL`].
+    ///
+    /// Like the other `UserSliceReader` methods, data races are allowed on the userspace address.
+    pub fn copy_from_user_slice(
+        &mut self,
+        reader: &mut UserSliceReader,
+        offset: usize,
+        len: usize,
+    ) -> Result {
+        // SAFETY: `&mut self` guarantees that nothing else accesses the page concurrently.
+        unsafe { self.copy_from_user_slice_raw(reader, offset, len) }
+    }
+
+    /// Copies data from this page into userspace.
+    ///
+    /// This method will perform bounds checks on the page offset. If `offset .. offset+len` goes
+    /// outside of the page, then this call returns [`EINVAL`].
+    ///
+    /// Like the other `UserSliceWriter` methods, data races are allowed on the userspace address.
+    pub fn copy_to_user_slice(
+        &self,
+        writer: &mut UserSliceWriter,
+        offset: usize,
+        len: usize,
+    ) -> Result {
+        self.with_pointer_into_page(offset, len, move |src| {
+            // SAFETY: If `with_pointer_into_page` calls into this closure, then it has performed a
+            // bounds check and guarantees that `src` is valid for `len` bytes. Writers to the page
+            // either hold `&mut self` or use the `*_raw` methods, whose callers guarantee that
+            // they do not race with this read.
+            writer.write_slice(unsafe { core::slice::from_raw_parts(src, len) })
+        })
+    }
+}
+
+/// A page that is shared between several users, whose accesses are serialised by a mutex.
+///
+/// Locking it gives exclusive access to the [`Page`], so its safe accessors can be used. A mutex
+/// is used (instead of a spinlock) because copying from or to userspace may sleep.
+///
+/// # Examples
+///
+/// ```
+/// use kernel::page::{Page, SharedPage};
+/// use kernel::uaccess::UserSliceReader;
+///
+/// fn store(page: &SharedPage, reader: &mut UserSliceReader, len: usize) -> Result {
+///     page.lock().copy_from_user_slice(reader, 0, len)
+/// }
+///
+/// let page = Page::alloc_page(GFP_KERNEL | __GFP_ZERO)?;
+/// let page = Box::pin_init(SharedPage::new(page), GFP_KERNEL)?;
+/// page.lock().with_slice_mut(|data| data[0] = 1);
+/// assert_eq!(page.lock().with_slice(|data| data[0]), 1);
+/// # Ok::<(), Error>(())
+/// ```
+#[pin_data]
+pub struct SharedPage {
+    #[pin]
+    page: Mutex<Page>,
+}
+
+impl SharedPage {
+    /// Constructs a new shared page initialiser that takes ownership of `page`.
+    pub fn new(page: Page) -> impl PinInit<Self> {
+        pin_init!(Self {
+            page <- new_mutex!(page, "SharedPage::page"),
+        })
+    }
+
+    /// Acquires the lock of the page and gives the caller exclusive access to it.
+    pub fn lock(&self) -> Guard<'_, Page, MutexBackend> {
+        self.page.lock()
+    }
 }
 
 impl Drop for Page {
@@ -0,0 +1,394 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Per-CPU variables.
+//!
+//! Per-CPU variables have one instance of the value for each possible CPU. Code running on a CPU
+//! accesses that CPU's instance with preemption disabled (see [`get_cpu`]), so no cache lines are
+//! bounced between CPUs. Aggregating the values of all CPUs is done by iterating over the possible
+//! CPUs (see [`PerCpu::for_each_possible_cpu`]).
+//!
+//! Values may be accessed concurrently: by the owning CPU (including from interrupt context) and
+//! by other CPUs when aggregating. So only shared references are handed out, and the values
+//! usually use atomics for interior mutability. Since atomics on the local CPU's instance are not
+//! contended, they are cheap.
+//!
+//! Dynamic per-CPU variables are allocated with [`PerCpu`], static ones are defined with
+//! [`define_per_cpu`].
+//!
+//! C header: [`include/linux/percpu.h`](srctree/include/linux/percpu.h)
+
+use crate::{
+    alloc::{AllocError, Flags},
+    bindings,
+};
+use core::{
+    cell::UnsafeCell,
+    ffi::{c_int, c_void},
+    marker::PhantomData,
+    mem::{align_of, size_of},
+    ptr::{self, NonNull},
+};
+
+/// Returns the number of CPU ids, i.e., one more than the highest possible CPU id.
+#[inline]
+pub fn nr_cpu_ids() -> u32 {
+    // SAFETY: FFI call without safety requirements. `nr_cpu_ids` is either a constant or only
+    // written during boot.
+    unsafe { bindings::nr_cpu_ids() }
+}
+
+/// An iterator over the ids of the possible CPUs.
+///
+/// It is created by [`possible_cpus`].
+pub struct PossibleCpus {
+    prev: c_int,
+}
+
+impl Iterator for PossibleCpus {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        // SAFETY: `__cpu_possible_mask` is a valid cpumask that is only written during boot.
+        let cpu = unsafe {
+            bindings::cpumask_next(self.prev, ptr::addr_of!(bindings::__cpu_possible_mask))
+        };
+        if cpu >= nr_cpu_ids() {
+            return None;
+        }
+        self.prev = cpu as c_int;
+        Some(cpu)
+    }
+}
+
+/// Returns an iterator over the ids of the possible CPUs.
+pub fn possible_cpus() -> PossibleCpus {
+    PossibleCpus { prev: -1 }
+}
+
+/// Calls the given closure with the id of every possible CPU.
+pub fn for_each_possible_cpu(f: impl FnMut(u32)) {
+    possible_cpus().for_each(f)
+}
+
+/// A guard that keeps the current task on its CPU by disabling preemption.
+///
+/// It is created by [`get_cpu`] and enables preemption again when dropped.
+///
+/// # Invariants
+///
+/// Preemption is disabled while the guard exists, and `cpu` is the id of the CPU the guard was
+/// created on.
+#[must_use = "preemption is enabled immediately when the guard is unused"]
+pub struct CpuGuard {
+    cpu: u32,
+    _not_send: PhantomData<*mut ()>,
+}
+
+/// Disables preemption and returns a guard holding the id of the current CPU.
+///
+/// Since preemption is disabled, the caller must not sleep while holding the returned guard.
+pub fn get_cpu() -> CpuGuard {
+    // INVARIANT: `get_cpu` disables preemption and returns the id of the current CPU.
+    // SAFETY: FFI call without safety requirements.
+    let cpu = unsafe { bindings::get_cpu() };
+    CpuGuard {
+        cpu: cpu as u32,
+        _not_send: PhantomData,
+    }
+}
+
+impl CpuGuard {
+    /// Returns the id of the CPU the current task is running on.
+    pub fn cpu(&self) -> u32 {
+        self.cpu
+    }
+}
+
+impl Drop for CpuGuard {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariant, preemption was disabled by `get_cpu` on this task, since
+        // the guard is not `Send`.
+        unsafe { bindings::put_cpu() };
+    }
+}
+
+/// Returns a pointer to the instance of the per-CPU variable at `ptr` that belongs to `cpu`.
+///
+/// # Safety
+///
+/// `ptr` must be a per-CPU address and `cpu` must be a possible CPU.
+unsafe fn per_cpu_ptr<T>(ptr: *const T, cpu: u32) -> *const T {
+    // SAFETY: The safety requirements are forwarded to the caller.
+    unsafe { bindings::per_cpu_ptr(ptr as *mut c_void, cpu) as *const T }
+}
+
+/// Returns whether `cpu` is a possible CPU (`cpu_possible`).
+///
+/// The possible CPUs may have holes, so a CPU id below [`nr_cpu_ids`] is not necessarily possible.
+pub fn cpu_possible(cpu: u32) -> bool {
+    if cpu >= nr_cpu_ids() {
+        return false;
+    }
+
+    // SAFETY: FFI call without safety requirements. `cpu` is below `nr_cpu_ids`, as required by
+    // the cpumask functions.
+    unsafe { bindings::cpu_possible(cpu) }
+}
+
+/// A dynamically allocated per-CPU variable.
+///
+/// # Invariants
+///
+/// `ptr` is a per-CPU address returned by `__alloc_percpu_gfp` and owned by this object. The
+/// instances of all possible CPUs are initialised.
+///
+/// # Examples
+///
+/// The following example keeps statistics in per-CPU counters, which are updated locally and
+/// summed up when read:
+///
+/// ```
+/// use core::sync::atomic::{AtomicU64, Ordering};
+/// use kernel::percpu::{get_cpu, PerCpu};
+///
+/// struct Stats {
+///     packets: PerCpu<AtomicU64>,
+/// }
+///
+/// impl Stats {
+///     fn new() -> Result<Self> {
+///         Ok(Self {
+///             packets: PerCpu::new(GFP_KERNEL, |_| AtomicU64::new(0))?,
+///         })
+///     }
+///
+///     fn inc(&self) {
+///         let cpu = get_cpu();
+///         self.packets.get(&cpu).fetch_add(1, Ordering::Relaxed);
+///     }
+///
+///     fn total(&self) -> u64 {
+///         let mut sum = 0;
+///         self.packets.for_each_possible_cpu(|_, v| sum += v.load(Ordering::Relaxed));
+///         sum
+///     }
+/// }
+///
+/// let stats = Stats::new()?;
+/// stats.inc();
+/// stats.inc();
+/// assert_eq!(stats.total(), 2);
+/// # Ok::<(), Error>(())
+/// ```
+pub struct PerCpu<T> {
+    ptr: NonNull<T>,
+    _p: PhantomData<T>,
+}
+
+// SAFETY: `PerCpu` owns the instances of all CPUs, so it can be sent to another thread if they
+// can. The instances may be accessed from several CPUs through shared references, so `T` also
+// needs to be `Sync`.
+unsafe impl<T: Send + Sync> Send for PerCpu<T> {}
+
+// SAFETY: Shared references to `PerCpu` only give out shared references to the instances, which
+// may be accessed from several CPUs concurrently, so `T` needs to be `Sync`. `T` needs to be
+// `Send` because the instances of all CPUs are dropped by whichever thread drops the `PerCpu`.
+unsafe impl<T: Send + Sync> Sync for PerCpu<T> {}
+
+impl<T> PerCpu<T> {
+    /// Allocates a new per-CPU variable, initialising the instance of every possible CPU with the
+    /// value returned by `init` for that CPU.
+    pub fn new(flags: Flags, mut init: impl FnMut(u32) -> T) -> Result<Self, AllocError> {
+        // SAFETY: FFI call without safety requirements. Zero-sized requests are rounded up since
+        // the per-CPU allocator does not support them.
+        let ptr = unsafe {
+            bindings::__alloc_percpu_gfp(size_of::<T>().max(1), align_of::<T>(), flags.as_raw())
+        };
+        let ptr = NonNull::new(ptr.cast::<T>()).ok_or(AllocError)?;
+
+        for cpu in possible_cpus() {
+            // SAFETY: `ptr` was just allocated by the per-CPU allocator and `cpu` is possible,
+            // so the pointer is valid for writes and properly aligned.
+            unsafe { per_cpu_ptr(ptr.as_ptr(), cpu).cast_mut().write(init(cpu)) };
+        }
+
+        // INVARIANT: The instances of all possible CPUs were initialised above.
+        Ok(Self {
+            ptr,
+            _p: PhantomData,
+        })
+    }
+
+    /// Returns the instance that belongs to the CPU the current task is running on.
+    ///
+    /// The guard ensures that the task stays on that CPU for the lifetime of the reference.
+    pub fn get<'a>(&'a self, guard: &'a CpuGuard) -> &'a T {
+        // SAFETY: By the type invariants, `ptr` is a per-CPU address whose instances are
+        // initialised, and `guard.cpu()` is the current CPU, which is possible.
+        unsafe { &*per_cpu_ptr(self.ptr.as_ptr(), guard.cpu()) }
+    }
+
+    /// Returns the instance that belongs to the given CPU, or [`None`] if it is not possible.
+    pub fn get_remote(&self, cpu: u32) -> Option<&T> {
+        if !cpu_possible(cpu) {
+            return None;
+        }
+
+        // SAFETY: By the type invariants, `ptr` is a per-CPU address whose instances are
+        // initialised, and `cpu` was checked to be possible.
+        Some(unsafe { &*per_cpu_ptr(self.ptr.as_ptr(), cpu) })
+    }
+
+    /// Returns a mutable reference to the instance that belongs to the given CPU, or [`None`] if
+    /// it is not possible.
+    ///
+    /// No other references to the instances can exist since `self` is borrowed mutably.
+    pub fn get_mut(&mut self, cpu: u32) -> Option<&mut T> {
+        if !cpu_possible(cpu) {
+            return None;
+        }
+
+        // SAFETY: By the type invariants, `ptr` is a per-CPU address whose instances are
+        // initialised, and `cpu` was checked to be possible. The mutable borrow of `self` ensures
+        // exclusive access.
+        Some(unsafe { &mut *per_cpu_ptr(self.ptr.as_ptr(), cpu).cast_mut() })
+    }
+
+    /// Calls the given closure with the id and the instance of every possible CPU.
+    pub fn for_each_possible_cpu(&self, mut f: impl FnMut(u32, &T)) {
+        for cpu in possible_cpus() {
+            // SAFETY: By the type invariants, `ptr` is a per-CPU address whose instances are
+            // initialised, and `cpu` is possible.
+            f(cpu, unsafe { &*per_cpu_ptr(self.ptr.as_ptr(), cpu) });
+        }
+    }
+}
+
+impl<T> Drop for PerCpu<T> {
+    fn drop(&mut self) {
+        for cpu in possible_cpus() {
+            // SAFETY: By the type invariants, the instance of every possible CPU is initialised,
+            // and it is not used anymore.
+            unsafe { ptr::drop_in_place(per_cpu_ptr(self.ptr.as_ptr(), cpu).cast_mut()) };
+        }
+
+        // SAFETY: By the type invariants, `ptr` was allocated by `__alloc_percpu_gfp`.
+        unsafe { bindings::free_percpu(self.ptr.as_ptr().cast()) };
+    }
+}
+
+/// The storage of a static per-CPU variable.
+///
+/// It is placed in the per-CPU section by [`define_per_cpu`], which copies it for each CPU. Its
+/// address is therefore a per-CPU address, which must not be dereferenced directly.
+#[doc(hidden)]
+#[repr(transparent)]
+pub struct StaticPerCpuSymbol<T>(UnsafeCell<T>);
+
+impl<T> StaticPerCpuSymbol<T> {
+    #[doc(hidden)]
+    pub const fn new(val: T) -> Self {
+        Self(UnsafeCell::new(val))
+    }
+}
+
+/// A static per-CPU variable.
+///
+/// Instances are defined with the [`define_per_cpu`] macro.
+///
+/// # Invariants
+///
+/// `ptr` is the per-CPU address of a [`StaticPerCpuSymbol<T>`] in the per-CPU section.
+pub struct StaticPerCpu<T: 'static> {
+    ptr: *const StaticPerCpuSymbol<T>,
+}
+
+// SAFETY: Only shared references to the instances are given out, and they may be accessed from
+// several CPUs concurrently, so `T` needs to be `Sync`. The instances are never dropped.
+unsafe impl<T: Sync> Sync for StaticPerCpu<T> {}
+
+impl<T> StaticPerCpu<T> {
+    /// Creates a new static per-CPU variable from its storage.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be the address of a [`StaticPerCpuSymbol<T>`] placed in the per-CPU section.
+    #[doc(hidden)]
+    pub const unsafe fn from_symbol(ptr: *const StaticPerCpuSymbol<T>) -> Self {
+        // INVARIANT: The safety requirements guarantee the invariant.
+        Self { ptr }
+    }
+
+    fn cpu_ptr(&self, cpu: u32) -> *const T {
+        // SAFETY: By the type invariants, `ptr` is a per-CPU address. The caller ensures that
+        // `cpu` is possible.
+        let ptr = unsafe { per_cpu_ptr(self.ptr, cpu) };
+        // SAFETY: The pointer points to a valid instance of `StaticPerCpuSymbol<T>`.
+        unsafe { (*ptr).0.get() }
+    }
+
+    /// Returns the instance that belongs to the CPU the current task is running on.
+    ///
+    /// The guard ensures that the task stays on that CPU for the lifetime of the reference.
+    pub fn get<'a>(&'static self, guard: &'a CpuGuard) -> &'a T {
+        // SAFETY: The instance of a possible CPU is valid and never dropped, and `guard.cpu()` is
+        // the current CPU, which is possible. Only shared references are ever given out.
+        unsafe { &*self.cpu_ptr(guard.cpu()) }
+    }
+
+    /// Returns the instance that belongs to the given CPU, or [`None`] if it is not possible.
+    pub fn get_remote(&'static self, cpu: u32) -> Option<&'static T> {
+        if !cpu_possible(cpu) {
+            return None;
+        }
+
+        // SAFETY: The instance of a possible CPU is valid and never dropped. Only shared
+        // references are ever given out.
+        Some(unsafe { &*self.cpu_ptr(cpu) })
+    }
+
+    /// Calls the given closure with the id and the instance of every possible CPU.
+    pub fn for_each_possible_cpu(&'static self, mut f: impl FnMut(u32, &T)) {
+        for cpu in possible_cpus() {
+            // SAFETY: The instance of a possible CPU is valid and never dropped. Only shared
+            // references are ever given out.
+            f(cpu, unsafe { &*self.cpu_ptr(cpu) });
+        }
+    }
+}
+
+/// Defines a static per-CPU variable.
+///
+/// The initial value is copied to the instance of every CPU, and the instances are never dropped.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::{AtomicU32, Ordering};
+/// use kernel::percpu::{define_per_cpu, get_cpu};
+///
+/// define_per_cpu!(static EVENTS: AtomicU32 = AtomicU32::new(0));
+///
+/// {
+///     let cpu = get_cpu();
+///     EVENTS.get(&cpu).fetch_add(1, Ordering::Relaxed);
+/// }
+///
+/// let mut sum = 0;
+/// EVENTS.for_each_possible_cpu(|_, v| sum += v.load(Ordering::Relaxed));
+/// assert_eq!(sum, 1);
+/// ```
+#[macro_export]
+macro_rules! define_per_cpu {
+    ($vis:vis static $name:ident: $ty:ty = $init:expr $(;)?) => {
+        $vis static $name: $crate::percpu::StaticPerCpu<$ty> = {
+            #[link_section = ".data..percpu"]
+            static mut SYMBOL: $crate::percpu::StaticPerCpuSymbol<$ty> =
+                $crate::percpu::StaticPerCpuSymbol::new($init);
+
+            // SAFETY: `SYMBOL` is placed in the per-CPU section above.
+            unsafe { $crate::percpu::StaticPerCpu::from_symbol(::core::ptr::addr_of!(SYMBOL)) }
+        };
+    };
+}
+pub use define_per_cpu;
@@ -0,0 +1,185 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Process identifiers and PID namespaces.
+//!
+//! C headers: [`include/linux/pid.h`](srctree/include/linux/pid.h) and
+//! [`include/linux/pid_namespace.h`](srctree/include/linux/pid_namespace.h).
+
+use crate::{
+    bindings,
+    task::{RawPid, Task},
+    types::{ARef, AlwaysRefCounted, Opaque},
+};
+use core::ptr::{self, NonNull};
+
+/// Wraps the kernel's `struct pid`.
+///
+/// Unlike a [`RawPid`], which may be reused by another task once its task exits, a [`Pid`] keeps
+/// identifying the same task for as long as a reference to it exists. It is thus the way to
+/// record a task and to check later whether it is still alive, without keeping it from exiting.
+///
+/// # Invariants
+///
+/// Instances of this type are always refcounted, that is, a call to `get_pid` ensures that the
+/// allocation remains valid at least until the matching call to `put_pid`.
+///
+/// # Examples
+///
+/// The following example shows how to record the process of the current task, and to validate
+/// it later:
+///
+/// ```
+/// use kernel::pid::Pid;
+/// use kernel::task::Task;
+///
+/// let sender = current!().group_leader().get_pid().ok_or(ESRCH)?;
+///
+/// // The recorded process is still alive, so its PID still refers to it.
+/// let task = sender.task().ok_or(ESRCH)?;
+/// assert!(core::ptr::eq(&*task, current!().group_leader()));
+///
+/// let nr = sender.nr_in_current_ns();
+/// assert_eq!(nr, current!().group_leader().pid_in_current_ns());
+/// let found = Pid::find_vpid(nr).ok_or(ESRCH)?;
+/// assert!(*found == *sender);
+/// assert!(Task::find_by_vpid(nr).is_some());
+///
+/// // The process is visible from its own PID namespace.
+/// let ns = current!().get_pid_ns().ok_or(ESRCH)?;
+/// assert_eq!(sender.nr_ns(&ns), nr);
+/// # Ok::<(), Error>(())
+/// ```
+#[repr(transparent)]
+pub struct Pid(Opaque<bindings::pid>);
+
+// SAFETY: By design, the only way to access a `Pid` is via an immutable reference or an `ARef`.
+// This means that the only situation in which a `Pid` can be accessed mutably is when the refcount
+// drops to zero and the destructor runs. It is safe for that to happen on any thread, so it is ok
+// for this type to be `Send`.
+unsafe impl Send for Pid {}
+
+// SAFETY: It's OK to access `Pid` through shared references from other threads because the
+// numbers it holds never change, and the tasks attached to it are protected by RCU on the C side.
+unsafe impl Sync for Pid {}
+
+impl Pid {
+    /// Looks up the PID with the number `nr` in the PID namespace of the current task
+    /// (`find_get_pid`).
+    ///
+    /// Returns [`None`] if there is no such PID.
+    pub fn find_vpid(nr: RawPid) -> Option<ARef<Self>> {
+        // SAFETY: `find_get_pid` may be called with any number, and returns either null or a new
+        // reference to a valid pid.
+        let ptr = unsafe { bindings::find_get_pid(nr) };
+
+        // SAFETY: If `ptr` is non-null, it is valid and we own the reference that was taken above.
+        NonNull::new(ptr).map(|ptr| unsafe { ARef::from_raw(ptr.cast()) })
+    }
+
+    /// Returns the number of the PID in the PID namespace of the current task (`pid_vnr`).
+    ///
+    /// Returns 0 if the PID is not visible from that namespace.
+    pub fn nr_in_current_ns(&self) -> RawPid {
+        // SAFETY: By the type invariant, we know that `self.0` is valid.
+        unsafe { bindings::pid_vnr(self.0.get()) }
+    }
+
+    /// Returns the number of the PID in the PID namespace `ns` (`pid_nr_ns`).
+    ///
+    /// Returns 0 if the PID is not visible from `ns`, which is the case when it belongs to a
+    /// sibling or a parent of `ns`.
+    pub fn nr_ns(&self, ns: &PidNamespace) -> RawPid {
+        // SAFETY: By the type invariants, we know that both `self.0` and `ns.0` are valid.
+        unsafe { bindings::pid_nr_ns(self.0.get(), ns.0.get()) }
+    }
+
+    /// Returns the task that the PID identifies (`get_pid_task`).
+    ///
+    /// Returns [`None`] if the task has exited.
+    pub fn task(&self) -> Option<ARef<Task>> {
+        // SAFETY: By the type invariant, we know that `self.0` is valid. `get_pid_task` returns
+        // either null or a new reference to a valid task.
+        let ptr = unsafe { bindings::get_pid_task(self.0.get(), bindings::pid_type_PIDTYPE_PID) };
+
+        // SAFETY: If `ptr` is non-null, it is valid and we own the reference that was taken above.
+        NonNull::new(ptr).map(|ptr| unsafe { ARef::from_raw(ptr.cast()) })
+    }
+}
+
+impl PartialEq for Pid {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self, other)
+    }
+}
+
+impl Eq for Pid {}
+
+// SAFETY: The type invariants guarantee that `Pid` is always refcounted.
+unsafe impl AlwaysRefCounted for Pid {
+    fn inc_ref(&self) {
+        // SAFETY: The existence of a shared reference means that the refcount is nonzero.
+        unsafe { bindings::get_pid(self.0.get()) };
+    }
+
+    unsafe fn dec_ref(obj: NonNull<Self>) {
+        // SAFETY: The safety requirements guarantee that the refcount is nonzero.
+        unsafe { bindings::put_pid(obj.cast().as_ptr()) }
+    }
+}
+
+/// Wraps the kernel's `struct pid_namespace`.
+///
+/// Tasks are given a number in the PID namespace they are created in, and in all the parents of
+/// that namespace. They are not visible from other namespaces.
+///
+/// # Invariants
+///
+/// Instances of this type are always refcounted, that is, a call to `get_pid_ns` ensures that
+/// the allocation remains valid at least until the matching call to `put_pid_ns`.
+#[repr(transparent)]
+pub struct PidNamespace(Opaque<bindings::pid_namespace>);
+
+// SAFETY: By design, the only way to access a `PidNamespace` is via an immutable reference or an
+// `ARef`. This means that the only situation in which a `PidNamespace` can be accessed mutably is
+// when the refcount drops to zero and the destructor runs. It is safe for that to happen on any
+// thread, so it is ok for this type to be `Send`.
+unsafe impl Send for PidNamespace {}
+
+// SAFETY: It's OK to access `PidNamespace` through shared references from other threads because
+// it is only used to look up numbers, which is properly synchronised by C code.
+unsafe impl Sync for PidNamespace {}
+
+impl PidNamespace {
+    /// Creates a reference to a [`PidNamespace`] from a valid pointer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `ptr` is valid and remains valid for the lifetime of the
+    /// returned [`PidNamespace`] reference.
+    pub unsafe fn from_ptr<'a>(ptr: *const bindings::pid_namespace) -> &'a Self {
+        // SAFETY: The safety requirements guarantee the validity of the dereference, while the
+        // `PidNamespace` type being transparent makes the cast ok.
+        unsafe { &*ptr.cast() }
+    }
+}
+
+impl PartialEq for PidNamespace {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self, other)
+    }
+}
+
+impl Eq for PidNamespace {}
+
+// SAFETY: The type invariants guarantee that `PidNamespace` is always refcounted.
+unsafe impl AlwaysRefCounted for PidNamespace {
+    fn inc_ref(&self) {
+        // SAFETY: The existence of a shared reference means that the refcount is nonzero.
+        unsafe { bindings::get_pid_ns(self.0.get()) };
+    }
+
+    unsafe fn dec_ref(obj: NonNull<Self>) {
+        // SAFETY: The safety requirements guarantee that the refcount is nonzero.
+        unsafe { bindings::put_pid_ns(obj.cast().as_ptr()) }
+    }
+}
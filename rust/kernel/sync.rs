@@ -8,15 +8,33 @@
 use crate::types::Opaque;
 
 mod arc;
+pub mod atomic;
+mod completion;
 mod condvar;
 pub mod lock;
 mod locked_by;
+pub mod poll;
+pub mod rcu;
+mod refcount;
+pub mod rwlock;
+mod seqcount;
+mod wait_queue;
 
-pub use arc::{Arc, ArcBorrow, UniqueArc};
+pub use arc::{Arc, ArcBorrow, UniqueArc, Weak};
+pub use atomic::Atomic;
+pub use completion::{new_completion, Completion};
 pub use condvar::{new_condvar, CondVar, CondVarTimeoutResult};
 pub use lock::mutex::{new_mutex, Mutex};
-pub use lock::spinlock::{new_spinlock, SpinLock};
+pub use lock::seqlock::{new_seqlock, SeqLock};
+pub use lock::spinlock::{
+    new_spinlock, new_spinlock_bh, new_spinlock_irq, SpinLock, SpinLockBh, SpinLockIrq,
+};
 pub use locked_by::LockedBy;
+pub use refcount::Refcount;
+pub use rwlock::semaphore::{new_rwsem, RwSemaphore};
+pub use rwlock::spinlock::{new_spin_rwlock, SpinRwLock};
+pub use seqcount::{new_seqcount, SeqCount, SeqCountWriteGuard};
+pub use wait_queue::{new_wait_queue, WaitQueue};
 
 /// Represents a lockdep class. It's a wrapper around C's `lock_class_key`.
 #[repr(transparent)]
@@ -43,6 +61,15 @@ impl Default for LockClassKey {
     }
 }
 
+/// Asserts that local interrupts are disabled.
+///
+/// It only has an effect when lockdep is enabled, in which case it warns if local interrupts are
+/// enabled.
+pub fn lockdep_assert_irqs_disabled() {
+    // SAFETY: FFI call without safety requirements.
+    unsafe { bindings::lockdep_assert_irqs_disabled() }
+}
+
 /// Defines a new static lock class and returns a pointer to it.
 #[doc(hidden)]
 #[macro_export]
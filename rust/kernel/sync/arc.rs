@@ -9,7 +9,7 @@
 //!
 //! It is different from the standard library's [`Arc`] in a few ways:
 //! 1. It is backed by the kernel's `refcount_t` type.
-//! 2. It does not support weak references, which allows it to be half the size.
+//! 2. It keeps the weak count in a separate `refcount_t`, next to the strong count.
 //! 3. It saturates the reference count instead of aborting when it goes over a threshold.
 //! 4. It does not provide a `get_mut` method, so the ref counted object is pinned.
 //! 5. The object in [`Arc`] is pinned implicitly.
@@ -20,10 +20,11 @@ use crate::{
     alloc::{box_ext::BoxExt, AllocError, Flags},
     bindings,
     init::{self, InPlaceInit, Init, PinInit},
+    sync::Refcount,
     try_init,
-    types::{ForeignOwnable, Opaque},
+    types::ForeignOwnable,
 };
-use alloc::boxed::Box;
+use alloc::{alloc::dealloc, boxed::Box};
 use core::{
     alloc::Layout,
     fmt,
@@ -131,14 +132,43 @@ pub struct Arc<T: ?Sized> {
     _p: PhantomData<ArcInner<T>>,
 }
 
+/// The allocation shared by [`Arc`] and [`Weak`] instances.
+///
+/// # Invariants
+///
+/// `refcount` is the number of [`Arc`] instances, and `data` is initialised while it is non-zero.
+/// `weak` is the number of [`Weak`] instances plus one while `refcount` is non-zero; the
+/// allocation is freed when it reaches zero.
 #[pin_data]
 #[repr(C)]
 struct ArcInner<T: ?Sized> {
-    refcount: Opaque<bindings::refcount_t>,
+    refcount: Refcount,
+    weak: Refcount,
     data: T,
 }
 
 impl<T: ?Sized> ArcInner<T> {
+    /// Releases one weak reference, freeing the allocation if it was the last one.
+    ///
+    /// # Safety
+    ///
+    /// The caller must own a weak reference to `ptr`, which is not used by the caller anymore.
+    /// `data` must have already been dropped if this is the last one.
+    unsafe fn release_weak(ptr: NonNull<ArcInner<T>>) {
+        // SAFETY: The caller owns a weak reference, so the allocation is still valid. Another
+        // thread may free it once the count is decremented, so only a raw pointer is kept.
+        let weak = unsafe { ptr.as_ref() }.weak.as_ptr();
+
+        // SAFETY: The caller owns a weak reference, so it is allowed to decrement the count.
+        if unsafe { bindings::refcount_dec_and_test(weak) } {
+            // SAFETY: This was the last reference, so nothing else accesses the allocation. `data`
+            // was already dropped, but only the pointer metadata is used to compute the layout.
+            let layout = Layout::for_value(unsafe { ptr.as_ref() });
+            // SAFETY: The allocation was created by `Box` with this layout.
+            unsafe { dealloc(ptr.as_ptr().cast(), layout) };
+        }
+    }
+
     /// Converts a pointer to the contents of an [`Arc`] into a pointer to the [`ArcInner`].
     ///
     /// # Safety
@@ -146,12 +176,14 @@ impl<T: ?Sized> ArcInner<T> {
     /// `ptr` must have been returned by a previous call to [`Arc::into_raw`], and the `Arc` must
     /// not yet have been destroyed.
     unsafe fn container_of(ptr: *const T) -> NonNull<ArcInner<T>> {
-        let refcount_layout = Layout::new::<bindings::refcount_t>();
+        // The fields before `data` are the same for all `T`, so the layout of `ArcInner<()>` is the
+        // layout of the header that precedes `data`.
+        let header_layout = Layout::new::<ArcInner<()>>();
         // SAFETY: The caller guarantees that the pointer is valid.
         let val_layout = Layout::for_value(unsafe { &*ptr });
         // SAFETY: We're computing the layout of a real struct that existed when compiling this
         // binary, so its layout is not so large that it can trigger arithmetic overflow.
-        let val_offset = unsafe { refcount_layout.extend(val_layout).unwrap_unchecked().1 };
+        let val_offset = unsafe { header_layout.extend(val_layout).unwrap_unchecked().1 };
 
         // Pointer casts leave the metadata unchanged. This is okay because the metadata of `T` and
         // `ArcInner<T>` is the same since `ArcInner` is a struct with `T` as its last field.
@@ -178,6 +210,10 @@ impl<T: ?Sized> core::ops::Receiver for Arc<T> {}
 // dynamically-sized type (DST) `U`.
 impl<T: ?Sized + Unsize<U>, U: ?Sized> core::ops::CoerceUnsized<Arc<U>> for Arc<T> {}
 
+// This is to allow coercion from `Weak<T>` to `Weak<U>` if `T` can be converted to the
+// dynamically-sized type (DST) `U`.
+impl<T: ?Sized + Unsize<U>, U: ?Sized> core::ops::CoerceUnsized<Weak<U>> for Weak<T> {}
+
 // This is to allow `Arc<U>` to be dispatched on when `Arc<T>` can be coerced into `Arc<U>`.
 impl<T: ?Sized + Unsize<U>, U: ?Sized> core::ops::DispatchFromDyn<Arc<U>> for Arc<T> {}
 
@@ -199,8 +235,8 @@ impl<T> Arc<T> {
     pub fn new(contents: T, flags: Flags) -> Result<Self, AllocError> {
         // INVARIANT: The refcount is initialised to a non-zero value.
         let value = ArcInner {
-            // SAFETY: There are no safety requirements for this FFI call.
-            refcount: Opaque::new(unsafe { bindings::REFCOUNT_INIT(1) }),
+            refcount: Refcount::new(1),
+            weak: Refcount::new(1),
             data: contents,
         };
 
@@ -270,10 +306,41 @@ impl<T: ?Sized> Arc<T> {
         core::ptr::eq(this.ptr.as_ptr(), other.ptr.as_ptr())
     }
 
+    /// Creates a new [`Weak`] pointer to this allocation.
+    ///
+    /// The [`Weak`] pointer does not keep the object alive, but can be upgraded back to an [`Arc`]
+    /// with [`Weak::upgrade`] while other [`Arc`] instances exist.
+    pub fn downgrade(this: &Self) -> Weak<T> {
+        // INVARIANT: `Refcount` saturates, so it cannot overflow to zero.
+        // SAFETY: By the type invariant, there is necessarily a reference to the object, so it is
+        // safe to increment the weak count.
+        unsafe { this.ptr.as_ref() }.weak.inc();
+
+        // INVARIANT: We just incremented the weak count. This increment is now owned by the new
+        // `Weak`.
+        Weak { ptr: this.ptr }
+    }
+
+    /// Drops the object and releases the weak reference collectively held by [`Arc`] instances.
+    ///
+    /// # Safety
+    ///
+    /// The strong count must have just reached zero, and `ptr` must not be used anymore.
+    unsafe fn drop_slow(ptr: NonNull<ArcInner<T>>) {
+        // SAFETY: The strong count reached zero, so no other `Arc` accesses the object, and it is
+        // never accessed again.
+        unsafe { core::ptr::drop_in_place(core::ptr::addr_of_mut!((*ptr.as_ptr()).data)) };
+
+        // SAFETY: All `Arc` instances collectively own a weak reference, and the last one is gone.
+        unsafe { ArcInner::release_weak(ptr) };
+    }
+
     /// Converts this [`Arc`] into a [`UniqueArc`], or destroys it if it is not unique.
     ///
     /// When this destroys the `Arc`, it does so while properly avoiding races. This means that
-    /// this method will never call the destructor of the value.
+    /// this method will never call the destructor of the value, unless there are [`Weak`]
+    /// references to it. In that case, the last `Arc` cannot become unique since a [`Weak`] could
+    /// otherwise upgrade it, so the value is dropped and [`None`] is returned.
     ///
     /// # Examples
     ///
@@ -304,11 +371,24 @@ impl<T: ?Sized> Arc<T> {
     ///
     /// # Ok::<(), Error>(())
     /// ```
+    ///
+    /// ```
+    /// use kernel::sync::{Arc, UniqueArc};
+    ///
+    /// let arc = Arc::new(42, GFP_KERNEL)?;
+    /// let weak = Arc::downgrade(&arc);
+    ///
+    /// // The conversion fails since `weak` could otherwise upgrade the unique arc.
+    /// assert!(arc.into_unique_or_drop().is_none());
+    /// assert!(weak.upgrade().is_none());
+    ///
+    /// # Ok::<(), Error>(())
+    /// ```
     pub fn into_unique_or_drop(self) -> Option<Pin<UniqueArc<T>>> {
         // We will manually manage the refcount in this method, so we disable the destructor.
         let me = ManuallyDrop::new(self);
         // SAFETY: We own a refcount, so the pointer is still valid.
-        let refcount = unsafe { me.ptr.as_ref() }.refcount.get();
+        let refcount = unsafe { me.ptr.as_ref() }.refcount.as_ptr();
 
         // If the refcount reaches a non-zero value, then we have destroyed this `Arc` and will
         // return without further touching the `Arc`. If the refcount reaches zero, then there are
@@ -317,9 +397,19 @@ impl<T: ?Sized> Arc<T> {
         // SAFETY: We own a refcount, so the pointer is not dangling.
         let is_zero = unsafe { bindings::refcount_dec_and_test(refcount) };
         if is_zero {
-            // SAFETY: We have exclusive access to the arc, so we can perform unsynchronized
-            // accesses to the refcount.
-            unsafe { core::ptr::write(refcount, bindings::REFCOUNT_INIT(1)) };
+            // No new `Weak` can be created once the refcount is zero, so the weak count can only
+            // decrease from now on.
+            //
+            // SAFETY: The allocation is kept alive by the weak reference held by all `Arc`s.
+            let weak = unsafe { me.ptr.as_ref() }.weak.read();
+            if weak != 1 {
+                // SAFETY: The refcount just reached zero, and `me` is not used anymore.
+                unsafe { Self::drop_slow(me.ptr) };
+                return None;
+            }
+
+            // SAFETY: We have exclusive access to the arc, so the allocation is still valid.
+            unsafe { me.ptr.as_ref() }.refcount.set(1);
 
             // INVARIANT: We own the only refcount to this arc, so we may create a `UniqueArc`. We
             // must pin the `UniqueArc` because the values was previously in an `Arc`, and they pin
@@ -376,10 +466,10 @@ impl<T: ?Sized> AsRef<T> for Arc<T> {
 
 impl<T: ?Sized> Clone for Arc<T> {
     fn clone(&self) -> Self {
-        // INVARIANT: C `refcount_inc` saturates the refcount, so it cannot overflow to zero.
+        // INVARIANT: `Refcount` saturates, so it cannot overflow to zero.
         // SAFETY: By the type invariant, there is necessarily a reference to the object, so it is
         // safe to increment the refcount.
-        unsafe { bindings::refcount_inc(self.ptr.as_ref().refcount.get()) };
+        unsafe { self.ptr.as_ref() }.refcount.inc();
 
         // SAFETY: We just incremented the refcount. This increment is now owned by the new `Arc`.
         unsafe { Self::from_inner(self.ptr) }
@@ -392,17 +482,18 @@ impl<T: ?Sized> Drop for Arc<T> {
         // touch `refcount` after it's decremented to a non-zero value because another thread/CPU
         // may concurrently decrement it to zero and free it. It is ok to have a raw pointer to
         // freed/invalid memory as long as it is never dereferenced.
-        let refcount = unsafe { self.ptr.as_ref() }.refcount.get();
+        let refcount = unsafe { self.ptr.as_ref() }.refcount.as_ptr();
 
         // INVARIANT: If the refcount reaches zero, there are no other instances of `Arc`, and
         // this instance is being dropped, so the broken invariant is not observable.
         // SAFETY: Also by the type invariant, we are allowed to decrement the refcount.
         let is_zero = unsafe { bindings::refcount_dec_and_test(refcount) };
         if is_zero {
-            // The count reached zero, we must free the memory.
+            // The count reached zero, we must drop the object, and free the memory if there are no
+            // weak references.
             //
-            // SAFETY: The pointer was initialised from the result of `Box::leak`.
-            unsafe { drop(Box::from_raw(self.ptr.as_ptr())) };
+            // SAFETY: The refcount just reached zero, and `self.ptr` is not used anymore.
+            unsafe { Self::drop_slow(self.ptr) };
         }
     }
 }
@@ -420,6 +511,159 @@ impl<T: ?Sized> From<Pin<UniqueArc<T>>> for Arc<T> {
     }
 }
 
+/// A weak reference to an object owned by [`Arc`] instances.
+///
+/// It keeps the allocation alive, but not the object: the object is dropped when the last [`Arc`]
+/// is dropped, even if [`Weak`] references remain. [`Weak::upgrade`] returns a new [`Arc`] while
+/// the object is alive, and [`None`] afterwards. Weak references are created with
+/// [`Arc::downgrade`].
+///
+/// # Invariants
+///
+/// `ptr` points to a valid allocation, and the weak count accounts for this instance.
+///
+/// # Examples
+///
+/// Upgrading fails once the last [`Arc`] is dropped:
+///
+/// ```
+/// use core::sync::atomic::{AtomicUsize, Ordering};
+/// use kernel::sync::{Arc, Weak};
+///
+/// static DROPS: AtomicUsize = AtomicUsize::new(0);
+///
+/// struct Node(u32);
+///
+/// impl Drop for Node {
+///     fn drop(&mut self) {
+///         DROPS.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let obj = Arc::new(Node(10), GFP_KERNEL)?;
+/// let weak: Weak<Node> = Arc::downgrade(&obj);
+/// let weak2 = weak.clone();
+///
+/// let upgraded = weak.upgrade().unwrap();
+/// assert!(Arc::ptr_eq(&obj, &upgraded));
+///
+/// // The object remains alive while there is an `Arc`.
+/// drop(obj);
+/// assert_eq!(DROPS.load(Ordering::Relaxed), 0);
+/// assert_eq!(weak2.upgrade().map(|n| n.0), Some(10));
+///
+/// // It is dropped with the last `Arc`, even though weak references remain.
+/// drop(upgraded);
+/// assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+/// assert!(weak.upgrade().is_none());
+/// assert!(weak2.upgrade().is_none());
+/// # Ok::<(), Error>(())
+/// ```
+///
+/// Upgrades racing with the drop of the last [`Arc`] either get the live object or fail, and the
+/// object is dropped exactly once:
+///
+/// ```
+/// use core::sync::atomic::{AtomicUsize, Ordering};
+/// use kernel::sync::{new_completion, Arc, Completion};
+/// use kernel::time::{delay::msleep, Delta};
+/// use kernel::workqueue;
+///
+/// static DROPS: AtomicUsize = AtomicUsize::new(0);
+///
+/// struct Node(u32);
+///
+/// impl Drop for Node {
+///     fn drop(&mut self) {
+///         DROPS.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let done = Arc::pin_init(new_completion!(), GFP_KERNEL)?;
+/// let obj = Arc::new(Node(20), GFP_KERNEL)?;
+/// let weak = Arc::downgrade(&obj);
+/// let weak2 = weak.clone();
+///
+/// let worker_done = done.clone();
+/// workqueue::system().try_spawn(GFP_KERNEL, move || {
+///     drop(obj);
+///     drop(weak2);
+///     worker_done.complete();
+/// })?;
+///
+/// // Sleep between the upgrades, so that the work item gets to run on non-preemptible kernels.
+/// while let Some(node) = weak.upgrade() {
+///     assert_eq!(node.0, 20);
+///     drop(node);
+///     msleep(Delta::from_millis(1));
+/// }
+///
+/// done.wait();
+/// assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+/// assert!(weak.upgrade().is_none());
+/// # Ok::<(), Error>(())
+/// ```
+pub struct Weak<T: ?Sized> {
+    ptr: NonNull<ArcInner<T>>,
+}
+
+// SAFETY: `Weak<T>` may be upgraded to `Arc<T>` on any thread, so it has the same requirements as
+// `Arc<T>`.
+unsafe impl<T: ?Sized + Sync + Send> Send for Weak<T> {}
+
+// SAFETY: `&Weak<T>` may be upgraded to `Arc<T>` on any thread, so it has the same requirements as
+// `&Arc<T>`.
+unsafe impl<T: ?Sized + Sync + Send> Sync for Weak<T> {}
+
+impl<T: ?Sized> Weak<T> {
+    /// Tries to get an [`Arc`] to the object.
+    ///
+    /// Returns [`None`] if the object has already been dropped.
+    pub fn upgrade(&self) -> Option<Arc<T>> {
+        // SAFETY: By the type invariant, the allocation is still valid. The refcount is only
+        // incremented if the object is still alive.
+        if unsafe { self.ptr.as_ref() }.refcount.inc_not_zero() {
+            // SAFETY: We just incremented the refcount from a non-zero value. This increment is
+            // now owned by the new `Arc`.
+            Some(unsafe { Arc::from_inner(self.ptr) })
+        } else {
+            None
+        }
+    }
+
+    /// Compare whether two [`Weak`] pointers reference the same allocation.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        core::ptr::eq(this.ptr.as_ptr(), other.ptr.as_ptr())
+    }
+}
+
+impl<T: ?Sized> Clone for Weak<T> {
+    fn clone(&self) -> Self {
+        // INVARIANT: `Refcount` saturates, so it cannot overflow to zero.
+        // SAFETY: By the type invariant, the allocation is still valid and the weak count is
+        // non-zero.
+        unsafe { self.ptr.as_ref() }.weak.inc();
+
+        // INVARIANT: We just incremented the weak count. This increment is now owned by the new
+        // `Weak`.
+        Self { ptr: self.ptr }
+    }
+}
+
+impl<T: ?Sized> Drop for Weak<T> {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariant, we own a weak reference. If it is the last one, then the
+        // last `Arc` is gone too, so the object has been dropped.
+        unsafe { ArcInner::release_weak(self.ptr) };
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for Weak<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("(Weak)")
+    }
+}
+
 /// A borrowed reference to an [`Arc`] instance.
 ///
 /// For cases when one doesn't ever need to increment the refcount on the allocation, it is simpler
@@ -649,8 +893,8 @@ impl<T> UniqueArc<T> {
         // INVARIANT: The refcount is initialised to a non-zero value.
         let inner = Box::try_init::<AllocError>(
             try_init!(ArcInner {
-                // SAFETY: There are no safety requirements for this FFI call.
-                refcount: Opaque::new(unsafe { bindings::REFCOUNT_INIT(1) }),
+                refcount: Refcount::new(1),
+                weak: Refcount::new(1),
                 data <- init::uninit::<T, AllocError>(),
             }? AllocError),
             flags,
@@ -0,0 +1,192 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Atomic integers backed by the kernel's atomic types.
+//!
+//! Unlike the types in [`core::sync::atomic`], these use the kernel's own implementation of
+//! atomics, so they follow the [Linux kernel memory model] and are instrumented by tools such as
+//! KCSAN, like their C counterparts.
+//!
+//! C header: [`include/linux/atomic.h`](srctree/include/linux/atomic.h)
+//!
+//! [Linux kernel memory model]: srctree/tools/memory-model/Documentation/explanation.txt
+
+use crate::bindings;
+use core::cell::UnsafeCell;
+
+mod private {
+    pub trait Sealed {
+        type Repr;
+    }
+}
+
+/// An integer type that can be used with [`Atomic`].
+///
+/// This trait is sealed: it is implemented for [`i32`] (backed by `atomic_t`), [`i64`] (backed by
+/// `atomic64_t`) and [`usize`] (backed by the one of them that has the size of a pointer).
+pub trait AtomicType: Copy + private::Sealed {}
+
+/// An atomic integer.
+///
+/// Operations that do not return a value, as well as [`Atomic::load`] and [`Atomic::store`], are
+/// unordered (relaxed). [`Atomic::load_acquire`] and [`Atomic::store_release`] provide acquire and
+/// release ordering, and all other operations that return a value are fully ordered.
+///
+/// # Examples
+///
+/// ```
+/// use kernel::sync::Atomic;
+///
+/// static COUNT: Atomic<i32> = Atomic::new(0);
+///
+/// COUNT.add(2);
+/// assert_eq!(COUNT.fetch_add(3), 2);
+/// assert_eq!(COUNT.sub_return(1), 4);
+/// assert_eq!(COUNT.cmpxchg(4, 10), Ok(4));
+/// assert_eq!(COUNT.cmpxchg(4, 20), Err(10));
+/// assert_eq!(COUNT.xchg(0), 10);
+/// assert!(!COUNT.inc_not_zero());
+///
+/// let refs = Atomic::<usize>::new(1);
+/// assert!(refs.inc_not_zero());
+/// assert!(!refs.dec_and_test());
+/// assert!(refs.dec_and_test());
+/// assert_eq!(refs.load(), 0);
+/// ```
+#[repr(transparent)]
+pub struct Atomic<T: AtomicType>(UnsafeCell<<T as private::Sealed>::Repr>);
+
+// SAFETY: All accesses to the integer are atomic, so it can be shared between threads.
+unsafe impl<T: AtomicType> Sync for Atomic<T> {}
+
+macro_rules! impl_atomic {
+    ($type:ty, $repr:path, $int:ty, $prefix:ident) => {
+        impl private::Sealed for $type {
+            type Repr = $repr;
+        }
+
+        impl AtomicType for $type {}
+
+        $crate::macros::paste! {
+            // The casts are no-ops unless the C integer has a different signedness.
+            #[allow(clippy::unnecessary_cast)]
+            impl Atomic<$type> {
+                /// Creates a new atomic integer.
+                pub const fn new(value: $type) -> Self {
+                    Self(UnsafeCell::new($repr {
+                        counter: value as $int,
+                    }))
+                }
+
+                fn as_ptr(&self) -> *mut $repr {
+                    self.0.get()
+                }
+
+                /// Returns the value (`atomic_read`).
+                pub fn load(&self) -> $type {
+                    // SAFETY: `as_ptr` points to a valid atomic.
+                    unsafe { bindings::[<$prefix _read>](self.as_ptr()) } as $type
+                }
+
+                /// Returns the value with acquire ordering (`atomic_read_acquire`).
+                pub fn load_acquire(&self) -> $type {
+                    // SAFETY: `as_ptr` points to a valid atomic.
+                    unsafe { bindings::[<$prefix _read_acquire>](self.as_ptr()) } as $type
+                }
+
+                /// Sets the value (`atomic_set`).
+                pub fn store(&self, value: $type) {
+                    // SAFETY: `as_ptr` points to a valid atomic.
+                    unsafe { bindings::[<$prefix _set>](self.as_ptr(), value as $int) }
+                }
+
+                /// Sets the value with release ordering (`atomic_set_release`).
+                pub fn store_release(&self, value: $type) {
+                    // SAFETY: `as_ptr` points to a valid atomic.
+                    unsafe { bindings::[<$prefix _set_release>](self.as_ptr(), value as $int) }
+                }
+
+                /// Adds `value`, wrapping around on overflow (`atomic_add`).
+                pub fn add(&self, value: $type) {
+                    // SAFETY: `as_ptr` points to a valid atomic.
+                    unsafe { bindings::[<$prefix _add>](value as $int, self.as_ptr()) }
+                }
+
+                /// Subtracts `value`, wrapping around on overflow (`atomic_sub`).
+                pub fn sub(&self, value: $type) {
+                    // SAFETY: `as_ptr` points to a valid atomic.
+                    unsafe { bindings::[<$prefix _sub>](value as $int, self.as_ptr()) }
+                }
+
+                /// Adds `value` and returns the new value (`atomic_add_return`).
+                pub fn add_return(&self, value: $type) -> $type {
+                    // SAFETY: `as_ptr` points to a valid atomic.
+                    unsafe {
+                        bindings::[<$prefix _add_return>](value as $int, self.as_ptr())
+                    } as $type
+                }
+
+                /// Subtracts `value` and returns the new value (`atomic_sub_return`).
+                pub fn sub_return(&self, value: $type) -> $type {
+                    // SAFETY: `as_ptr` points to a valid atomic.
+                    unsafe {
+                        bindings::[<$prefix _sub_return>](value as $int, self.as_ptr())
+                    } as $type
+                }
+
+                /// Adds `value` and returns the previous value (`atomic_fetch_add`).
+                pub fn fetch_add(&self, value: $type) -> $type {
+                    // SAFETY: `as_ptr` points to a valid atomic.
+                    unsafe {
+                        bindings::[<$prefix _fetch_add>](value as $int, self.as_ptr())
+                    } as $type
+                }
+
+                /// Sets the value to `new` and returns the previous value (`atomic_xchg`).
+                pub fn xchg(&self, new: $type) -> $type {
+                    // SAFETY: `as_ptr` points to a valid atomic.
+                    unsafe { bindings::[<$prefix _xchg>](self.as_ptr(), new as $int) } as $type
+                }
+
+                /// Sets the value to `new` if it is `old` (`atomic_cmpxchg`).
+                ///
+                /// Returns `Ok(old)` if the value was updated, or `Err` with the current value
+                /// otherwise.
+                pub fn cmpxchg(&self, old: $type, new: $type) -> Result<$type, $type> {
+                    // SAFETY: `as_ptr` points to a valid atomic.
+                    let prev = unsafe {
+                        bindings::[<$prefix _cmpxchg>](self.as_ptr(), old as $int, new as $int)
+                    } as $type;
+                    if prev == old {
+                        Ok(prev)
+                    } else {
+                        Err(prev)
+                    }
+                }
+
+                /// Increments the value unless it is zero (`atomic_inc_not_zero`).
+                ///
+                /// Returns whether the value was incremented.
+                pub fn inc_not_zero(&self) -> bool {
+                    // SAFETY: `as_ptr` points to a valid atomic.
+                    unsafe { bindings::[<$prefix _inc_not_zero>](self.as_ptr()) }
+                }
+
+                /// Decrements the value (`atomic_dec_and_test`).
+                ///
+                /// Returns whether the new value is zero.
+                #[must_use = "use `sub` if the new value does not matter"]
+                pub fn dec_and_test(&self) -> bool {
+                    // SAFETY: `as_ptr` points to a valid atomic.
+                    unsafe { bindings::[<$prefix _dec_and_test>](self.as_ptr()) }
+                }
+            }
+        }
+    };
+}
+
+impl_atomic!(i32, bindings::atomic_t, i32, atomic);
+impl_atomic!(i64, bindings::atomic64_t, i64, atomic64);
+#[cfg(target_pointer_width = "32")]
+impl_atomic!(usize, bindings::atomic_t, i32, atomic);
+#[cfg(target_pointer_width = "64")]
+impl_atomic!(usize, bindings::atomic64_t, i64, atomic64);
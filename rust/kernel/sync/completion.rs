@@ -0,0 +1,181 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! A completion.
+//!
+//! This module allows Rust code to use the kernel's [`struct completion`] to wait for an event.
+//!
+//! [`struct completion`]: srctree/include/linux/completion.h
+
+use super::LockClassKey;
+use crate::{
+    error::{to_result, Result},
+    init::PinInit,
+    pin_init,
+    str::CStr,
+    time::{delta_to_timeout, jiffies_to_delta, Delta, Jiffies},
+    types::Opaque,
+};
+use core::marker::PhantomPinned;
+use macros::pin_data;
+
+/// Creates a [`Completion`] initialiser with the given name and a newly-created lock class.
+#[macro_export]
+macro_rules! new_completion {
+    ($($name:literal)?) => {
+        $crate::sync::Completion::new(
+            $crate::optional_name!($($name)?), $crate::static_lock_class!())
+    };
+}
+pub use new_completion;
+
+/// A completion.
+///
+/// Exposes the kernel's [`struct completion`]. It allows threads to wait until an event happens,
+/// which is signalled by another thread (possibly from interrupt context) with
+/// [`Completion::complete`] or [`Completion::complete_all`]. Unlike with a [`CondVar`], there is no
+/// need for extra state to remember that the event happened: a call to [`Completion::complete`]
+/// that happens before the wait is not lost.
+///
+/// Instances of [`Completion`] need a lock class and to be pinned. The recommended way to create
+/// such instances is with the [`pin_init`](crate::pin_init) and [`new_completion`] macros.
+///
+/// # Examples
+///
+/// The following example shows how a driver may wait for its device to be ready:
+///
+/// ```
+/// use kernel::sync::{new_completion, Completion};
+/// use kernel::time::Delta;
+///
+/// #[pin_data]
+/// struct Device {
+///     #[pin]
+///     ready: Completion,
+/// }
+///
+/// impl Device {
+///     // Called from the interrupt handler.
+///     fn irq(&self) {
+///         self.ready.complete();
+///     }
+///
+///     fn wait_ready(&self) -> Result {
+///         match self.ready.wait_timeout(Delta::from_millis(100)) {
+///             Some(_remaining) => Ok(()),
+///             None => Err(EIO),
+///         }
+///     }
+/// }
+///
+/// let dev = Box::pin_init(pin_init!(Device {
+///     ready <- new_completion!(),
+/// }), GFP_KERNEL)?;
+///
+/// assert!(dev.wait_ready().is_err());
+/// dev.irq();
+/// dev.wait_ready()?;
+///
+/// // The event is only signalled once.
+/// assert!(dev.wait_ready().is_err());
+///
+/// // Until `reinit`, every wait returns immediately after `complete_all`.
+/// dev.ready.complete_all();
+/// dev.ready.wait();
+/// dev.ready.wait();
+/// dev.ready.reinit();
+/// assert!(dev.wait_ready().is_err());
+/// # Ok::<(), Error>(())
+/// ```
+///
+/// [`CondVar`]: super::CondVar
+/// [`struct completion`]: srctree/include/linux/completion.h
+#[pin_data]
+pub struct Completion {
+    #[pin]
+    inner: Opaque<bindings::completion>,
+
+    /// A completion needs to be pinned because it contains a [`struct list_head`] that is
+    /// self-referential, so it cannot be safely moved once it is initialised.
+    ///
+    /// [`struct list_head`]: srctree/include/linux/types.h
+    #[pin]
+    _pin: PhantomPinned,
+}
+
+// SAFETY: `Completion` only uses a `struct completion`, which is safe to use on any thread.
+#[allow(clippy::non_send_fields_in_send_ty)]
+unsafe impl Send for Completion {}
+
+// SAFETY: `Completion` only uses a `struct completion`, which is safe to use on multiple threads
+// concurrently.
+unsafe impl Sync for Completion {}
+
+impl Completion {
+    /// Constructs a new completion initialiser.
+    pub fn new(name: &'static CStr, key: &'static LockClassKey) -> impl PinInit<Self> {
+        pin_init!(Self {
+            _pin: PhantomPinned,
+            // SAFETY: `slot` is valid while the closure is called and both `name` and `key` have
+            // static lifetimes so they live indefinitely.
+            inner <- Opaque::ffi_init(|slot| unsafe {
+                bindings::__init_completion(slot, name.as_char_ptr(), key.as_ptr())
+            }),
+        })
+    }
+
+    /// Signals the event, waking up one waiter.
+    ///
+    /// If there are no waiters, the next call to one of the wait functions returns immediately.
+    /// It may be called from any context.
+    pub fn complete(&self) {
+        // SAFETY: `inner` was initialised in the constructor.
+        unsafe { bindings::complete(self.inner.get()) };
+    }
+
+    /// Signals the event permanently, waking up all waiters.
+    ///
+    /// All current and future calls to the wait functions return immediately, until
+    /// [`Completion::reinit`] is called. It may be called from any context.
+    pub fn complete_all(&self) {
+        // SAFETY: `inner` was initialised in the constructor.
+        unsafe { bindings::complete_all(self.inner.get()) };
+    }
+
+    /// Resets the completion, so that it can be waited on again after [`Completion::complete_all`].
+    ///
+    /// The caller must make sure that there are no waiters and no concurrent calls to
+    /// [`Completion::complete`] or [`Completion::complete_all`], otherwise they may be lost.
+    pub fn reinit(&self) {
+        // SAFETY: `inner` was initialised in the constructor.
+        unsafe { bindings::reinit_completion(self.inner.get()) };
+    }
+
+    /// Waits for the event in uninterruptible mode.
+    pub fn wait(&self) {
+        // SAFETY: `inner` was initialised in the constructor.
+        unsafe { bindings::wait_for_completion(self.inner.get()) };
+    }
+
+    /// Waits for the event in interruptible mode.
+    ///
+    /// Returns [`ERESTARTSYS`](crate::error::code::ERESTARTSYS) if a signal arrived while waiting.
+    pub fn wait_interruptible(&self) -> Result {
+        // SAFETY: `inner` was initialised in the constructor.
+        to_result(unsafe { bindings::wait_for_completion_interruptible(self.inner.get()) })
+    }
+
+    /// Waits for the event in uninterruptible mode, for at most `timeout`.
+    ///
+    /// Returns the remaining time if the event was signalled, or [`None`] on timeout. The timeout
+    /// has jiffy granularity.
+    #[must_use = "wait_timeout returns None on timeout, so the caller must check the return value"]
+    pub fn wait_timeout(&self, timeout: Delta) -> Option<Delta> {
+        let jiffies = delta_to_timeout(timeout);
+
+        // SAFETY: `inner` was initialised in the constructor.
+        match unsafe { bindings::wait_for_completion_timeout(self.inner.get(), jiffies.as_raw()) } {
+            0 => None,
+            left => Some(jiffies_to_delta(Jiffies::from_raw(left))),
+        }
+    }
+}
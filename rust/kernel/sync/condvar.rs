@@ -11,10 +11,10 @@ use crate::{
     pin_init,
     str::CStr,
     task::{MAX_SCHEDULE_TIMEOUT, TASK_INTERRUPTIBLE, TASK_NORMAL, TASK_UNINTERRUPTIBLE},
-    time::Jiffies,
+    time::{delta_to_timeout, jiffies_to_delta, Delta, Jiffies},
     types::Opaque,
 };
-use core::ffi::{c_int, c_long};
+use core::ffi::{c_int, c_long, c_ulong};
 use core::marker::PhantomPinned;
 use core::ptr;
 use macros::pin_data;
@@ -165,19 +165,22 @@ impl CondVar {
     /// Atomically releases the given lock (whose ownership is proven by the guard) and puts the
     /// thread to sleep. It wakes up when notified by [`CondVar::notify_one`] or
     /// [`CondVar::notify_all`], or when a timeout occurs, or when the thread receives a signal.
+    ///
+    /// The timeout has jiffy granularity.
     #[must_use = "wait_interruptible_timeout returns if a signal is pending, so the caller must check the return value"]
     pub fn wait_interruptible_timeout<T: ?Sized, B: Backend>(
         &self,
         guard: &mut Guard<'_, T, B>,
-        jiffies: Jiffies,
+        timeout: Delta,
     ) -> CondVarTimeoutResult {
-        let jiffies = jiffies.try_into().unwrap_or(MAX_SCHEDULE_TIMEOUT);
+        let jiffies = delta_to_timeout(timeout).as_raw() as c_long;
         let res = self.wait_internal(TASK_INTERRUPTIBLE, guard, jiffies);
+        let remaining = jiffies_to_delta(Jiffies::from_raw(res as c_ulong));
 
-        match (res as Jiffies, crate::current!().signal_pending()) {
-            (jiffies, true) => CondVarTimeoutResult::Signal { jiffies },
+        match (res, crate::current!().signal_pending()) {
+            (_, true) => CondVarTimeoutResult::Signal { remaining },
             (0, false) => CondVarTimeoutResult::Timeout,
-            (jiffies, false) => CondVarTimeoutResult::Woken { jiffies },
+            (_, false) => CondVarTimeoutResult::Woken { remaining },
         }
     }
 
@@ -227,12 +230,12 @@ pub enum CondVarTimeoutResult {
     Timeout,
     /// Somebody woke us up.
     Woken {
-        /// Remaining sleep duration.
-        jiffies: Jiffies,
+        /// Remaining sleep duration, with jiffy granularity.
+        remaining: Delta,
     },
     /// A signal occurred.
     Signal {
-        /// Remaining sleep duration.
-        jiffies: Jiffies,
+        /// Remaining sleep duration, with jiffy granularity.
+        remaining: Delta,
     },
 }
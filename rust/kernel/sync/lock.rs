@@ -7,10 +7,11 @@
 
 use super::LockClassKey;
 use crate::{init::PinInit, pin_init, str::CStr, types::Opaque, types::ScopeGuard};
-use core::{cell::UnsafeCell, marker::PhantomData, marker::PhantomPinned};
+use core::{cell::UnsafeCell, marker::PhantomData, marker::PhantomPinned, ptr};
 use macros::pin_data;
 
 pub mod mutex;
+pub mod seqlock;
 pub mod spinlock;
 
 /// The "backend" of a lock.
@@ -58,6 +59,16 @@ pub unsafe trait Backend {
     #[must_use]
     unsafe fn lock(ptr: *mut Self::State) -> Self::GuardState;
 
+    /// Tries to acquire the lock without waiting, making the caller its owner on success.
+    ///
+    /// Returns the guard state if the lock was acquired, or [`None`] if it is contended.
+    ///
+    /// # Safety
+    ///
+    /// Callers must ensure that [`Backend::init`] has been previously called.
+    #[must_use]
+    unsafe fn try_lock(ptr: *mut Self::State) -> Option<Self::GuardState>;
+
     /// Releases the lock, giving up its ownership.
     ///
     /// # Safety
@@ -75,6 +86,13 @@ pub unsafe trait Backend {
         // SAFETY: The safety requirements ensure that the lock is initialised.
         *guard_state = unsafe { Self::lock(ptr) };
     }
+
+    /// Asserts that the lock is held by the current thread/CPU, when lockdep is enabled.
+    ///
+    /// # Safety
+    ///
+    /// Callers must ensure that [`Backend::init`] has been previously called.
+    unsafe fn assert_is_held(ptr: *mut Self::State);
 }
 
 /// A mutual exclusion primitive.
@@ -128,6 +146,27 @@ impl<T: ?Sized, B: Backend> Lock<T, B> {
         // SAFETY: The lock was just acquired.
         unsafe { Guard::new(self, state) }
     }
+
+    /// Asserts that the lock is held by the current thread/CPU.
+    ///
+    /// It only has an effect when lockdep is enabled, in which case it warns if the lock is not
+    /// held.
+    pub fn assert_is_held(&self) {
+        // SAFETY: The constructor of the type calls `init`, so the existence of the object proves
+        // that `init` was called.
+        unsafe { B::assert_is_held(self.state.get()) }
+    }
+
+    /// Tries to acquire the lock without waiting.
+    ///
+    /// Returns a guard that can be used to access the data protected by the lock if successful.
+    pub fn try_lock(&self) -> Option<Guard<'_, T, B>> {
+        // SAFETY: The constructor of the type calls `init`, so the existence of the object proves
+        // that `init` was called.
+        let state = unsafe { B::try_lock(self.state.get()) }?;
+        // SAFETY: The lock was just acquired.
+        Some(unsafe { Guard::new(self, state) })
+    }
 }
 
 /// A lock guard.
@@ -146,7 +185,52 @@ pub struct Guard<'a, T: ?Sized, B: Backend> {
 unsafe impl<T: Sync + ?Sized, B: Backend> Sync for Guard<'_, T, B> {}
 
 impl<T: ?Sized, B: Backend> Guard<'_, T, B> {
-    pub(crate) fn do_unlocked<U>(&mut self, cb: impl FnOnce() -> U) -> U {
+    /// Returns a proof that the lock is held, which lives as long as the borrow of the guard.
+    pub fn held(&self) -> &Held<T, B> {
+        // SAFETY: `Held` is a zero-sized type, so any non-null pointer is valid for a reference to
+        // it, and its alignment is 1.
+        unsafe { &*self.lock.data.get().cast::<Held<T, B>>() }
+    }
+
+    /// Returns a proof that the lock is held and that there are no other proofs derived from this
+    /// guard, which lives as long as the mutable borrow of the guard.
+    pub fn held_mut(&mut self) -> &mut Held<T, B> {
+        // SAFETY: `Held` is a zero-sized type, so any non-null pointer is valid for a reference to
+        // it, and its alignment is 1. The guard is borrowed mutably, so there are no other
+        // references derived from it.
+        unsafe { &mut *self.lock.data.get().cast::<Held<T, B>>() }
+    }
+
+    /// Releases the lock, calls `cb` and reacquires the lock before returning.
+    ///
+    /// The guard is borrowed mutably for the duration of the call, so the protected data cannot be
+    /// accessed through it while the lock is released. The lock is reacquired even if `cb`
+    /// unwinds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kernel::sync::{new_mutex, Mutex};
+    ///
+    /// fn flush(m: &Mutex<Vec<u8>>, write: impl Fn(&[u8])) -> Result {
+    ///     let mut guard = m.lock();
+    ///     while !guard.is_empty() {
+    ///         let mut chunk = Vec::new();
+    ///         chunk.extend_from_slice(&guard, GFP_KERNEL)?;
+    ///         guard.clear();
+    ///         // Other users may add more data while the chunk is being written.
+    ///         guard.do_unlocked(|| write(&chunk));
+    ///     }
+    ///     Ok(())
+    /// }
+    ///
+    /// let m = Box::pin_init(new_mutex!(Vec::new()), GFP_KERNEL)?;
+    /// m.lock().extend_from_slice(&[1, 2, 3], GFP_KERNEL)?;
+    /// flush(&m, |data| assert_eq!(data, &[1, 2, 3]))?;
+    /// assert!(m.lock().is_empty());
+    /// # Ok::<(), Error>(())
+    /// ```
+    pub fn do_unlocked<U>(&mut self, cb: impl FnOnce() -> U) -> U {
         // SAFETY: The caller owns the lock, so it is safe to unlock it.
         unsafe { B::unlock(self.lock.state.get(), &self.state) };
 
@@ -195,3 +279,42 @@ impl<'a, T: ?Sized, B: Backend> Guard<'a, T, B> {
         }
     }
 }
+
+/// A proof that a lock is held.
+///
+/// It is a zero-sized type that can only be obtained by reference, through [`Guard::held`] or
+/// [`Guard::held_mut`], so the proof cannot outlive the guard. The address of the reference is the
+/// address of the data protected by the lock, which identifies the lock without giving access to
+/// the data. It can be passed to functions that need to know that a lock is held, e.g.,
+/// [`LockedBy::access_held`], while the guard itself remains usable.
+///
+/// The type is not `Send` nor `Sync` because holding a lock is a per-thread/CPU property.
+///
+/// # Examples
+///
+/// ```
+/// use kernel::sync::lock::{mutex::MutexBackend, Held};
+/// use kernel::sync::{new_mutex, Mutex};
+///
+/// fn must_be_held(m: &Mutex<u32>, held: &Held<u32, MutexBackend>) {
+///     assert!(held.is_for(m));
+/// }
+///
+/// let m = Box::pin_init(new_mutex!(0u32), GFP_KERNEL)?;
+/// let mut guard = m.lock();
+/// must_be_held(&m, guard.held());
+/// *guard += 1;
+/// # Ok::<(), Error>(())
+/// ```
+///
+/// [`LockedBy::access_held`]: super::LockedBy::access_held
+pub struct Held<T: ?Sized, B: Backend> {
+    _p: PhantomData<(*mut (), *const T, B)>,
+}
+
+impl<T: ?Sized, B: Backend> Held<T, B> {
+    /// Returns whether this is a proof that `lock` is held.
+    pub fn is_for(&self, lock: &Lock<T, B>) -> bool {
+        ptr::eq(self, lock.data.get().cast::<Self>())
+    }
+}
@@ -4,6 +4,9 @@
 //!
 //! This module allows Rust code to use the kernel's `struct mutex`.
 
+use super::Guard;
+use crate::error::{code::*, Result};
+
 /// Creates a [`Mutex`] initialiser with the given name and a newly-created lock class.
 ///
 /// It uses the name if one is given, otherwise it generates one based on the file name and line
@@ -86,6 +89,51 @@ pub use new_mutex;
 /// [`struct mutex`]: srctree/include/linux/mutex.h
 pub type Mutex<T> = super::Lock<T, MutexBackend>;
 
+impl<T: ?Sized> super::Lock<T, MutexBackend> {
+    /// Acquires the mutex, unless the wait is interrupted by a signal.
+    ///
+    /// Returns [`ERESTARTSYS`] if a signal arrived while waiting, so that system calls are
+    /// transparently restarted once the signal is handled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kernel::sync::{new_mutex, Mutex};
+    ///
+    /// fn ioctl_set(m: &Mutex<u32>, value: u32) -> Result {
+    ///     *m.lock_interruptible()? = value;
+    ///     Ok(())
+    /// }
+    ///
+    /// let m = Box::pin_init(new_mutex!(0), GFP_KERNEL)?;
+    /// ioctl_set(&m, 10)?;
+    /// assert_eq!(*m.lock(), 10);
+    /// # Ok::<(), Error>(())
+    /// ```
+    pub fn lock_interruptible(&self) -> Result<Guard<'_, T, MutexBackend>> {
+        // SAFETY: The constructor of the type calls `init`, so the existence of the object proves
+        // that `init` was called.
+        if unsafe { bindings::mutex_lock_interruptible(self.state.get()) } != 0 {
+            return Err(ERESTARTSYS);
+        }
+        // SAFETY: The lock was just acquired.
+        Ok(unsafe { Guard::new(self, ()) })
+    }
+
+    /// Acquires the mutex, unless the wait is interrupted by a fatal signal.
+    ///
+    /// Returns [`EINTR`] if the task was killed while waiting.
+    pub fn lock_killable(&self) -> Result<Guard<'_, T, MutexBackend>> {
+        // SAFETY: The constructor of the type calls `init`, so the existence of the object proves
+        // that `init` was called.
+        if unsafe { bindings::mutex_lock_killable(self.state.get()) } != 0 {
+            return Err(EINTR);
+        }
+        // SAFETY: The lock was just acquired.
+        Ok(unsafe { Guard::new(self, ()) })
+    }
+}
+
 /// A kernel `struct mutex` lock backend.
 pub struct MutexBackend;
 
@@ -110,9 +158,22 @@ unsafe impl super::Backend for MutexBackend {
         unsafe { bindings::mutex_lock(ptr) };
     }
 
+    unsafe fn try_lock(ptr: *mut Self::State) -> Option<Self::GuardState> {
+        // SAFETY: The safety requirements of this function ensure that `ptr` points to valid
+        // memory, and that it has been initialised before.
+        let acquired = unsafe { bindings::mutex_trylock(ptr) } != 0;
+        acquired.then_some(())
+    }
+
     unsafe fn unlock(ptr: *mut Self::State, _guard_state: &Self::GuardState) {
         // SAFETY: The safety requirements of this function ensure that `ptr` is valid and that the
         // caller is the owner of the mutex.
         unsafe { bindings::mutex_unlock(ptr) };
     }
+
+    unsafe fn assert_is_held(ptr: *mut Self::State) {
+        // SAFETY: The safety requirements of this function ensure that `ptr` points to valid
+        // memory, and that it has been initialised before.
+        unsafe { bindings::mutex_assert_is_held(ptr) }
+    }
 }
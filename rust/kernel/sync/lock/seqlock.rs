@@ -0,0 +1,147 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! A kernel sequential lock.
+//!
+//! This module allows Rust code to use the kernel's `seqlock_t`.
+
+use core::mem::MaybeUninit;
+
+/// Creates a [`SeqLock`] initialiser with the given name and a newly-created lock class.
+///
+/// It uses the name if one is given, otherwise it generates one based on the file name and line
+/// number.
+#[macro_export]
+macro_rules! new_seqlock {
+    ($inner:expr $(, $name:literal)? $(,)?) => {
+        $crate::sync::SeqLock::new(
+            $inner, $crate::optional_name!($($name)?), $crate::static_lock_class!())
+    };
+}
+pub use new_seqlock;
+
+/// A sequential lock.
+///
+/// Exposes the kernel's [`seqlock_t`]. Writers are serialised by a spinlock, like [`SpinLock`],
+/// while readers never block writers: they take a snapshot of the data and retry if a writer was
+/// active in the meantime. It suits small data that is written rarely and read very often.
+///
+/// Writers use [`Lock::lock`](super::Lock::lock), and readers use [`SeqLock::read`]. Since
+/// readers may observe a torn copy that is then discarded, the data must be [`Copy`].
+///
+/// Instances of [`SeqLock`] need a lock class and to be pinned. The recommended way to create such
+/// instances is with the [`pin_init`](crate::pin_init) and [`new_seqlock`] macros.
+///
+/// # Examples
+///
+/// ```
+/// use kernel::sync::{new_seqlock, SeqLock};
+///
+/// #[derive(Clone, Copy)]
+/// struct Stats {
+///     packets: u64,
+///     bytes: u64,
+/// }
+///
+/// #[pin_data]
+/// struct Device {
+///     #[pin]
+///     stats: SeqLock<Stats>,
+/// }
+///
+/// let dev = Box::pin_init(pin_init!(Device {
+///     stats <- new_seqlock!(Stats { packets: 0, bytes: 0 }),
+/// }), GFP_KERNEL)?;
+///
+/// {
+///     let mut stats = dev.stats.lock();
+///     stats.packets += 1;
+///     stats.bytes += 1500;
+/// }
+///
+/// let avg = dev.stats.read(|s| s.bytes / s.packets);
+/// assert_eq!(avg, 1500);
+/// assert_eq!(dev.stats.get().packets, 1);
+/// # Ok::<(), Error>(())
+/// ```
+///
+/// [`SpinLock`]: super::SpinLock
+/// [`seqlock_t`]: srctree/include/linux/seqlock.h
+pub type SeqLock<T> = super::Lock<T, SeqLockBackend>;
+
+impl<T: Copy> SeqLock<T> {
+    /// Calls `f` with a consistent snapshot of the protected data and returns its result.
+    ///
+    /// It never blocks writers. If a writer modifies the data while the snapshot is taken, the
+    /// snapshot is discarded and taken again, so `f` is only called once, with consistent data.
+    pub fn read<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        loop {
+            // SAFETY: The constructor of the type calls `init`, so the existence of the object
+            // proves that `init` was called.
+            let seq = unsafe { bindings::read_seqbegin(self.state.get()) };
+
+            // SAFETY: `data` is valid for reads. A writer may be modifying it concurrently, so the
+            // copy is only treated as initialised once the sequence check below succeeds.
+            let copy =
+                unsafe { core::ptr::read_volatile(self.data.get().cast::<MaybeUninit<T>>()) };
+
+            // SAFETY: The lock was initialised (see above).
+            if unsafe { bindings::read_seqretry(self.state.get(), seq) } == 0 {
+                // SAFETY: No writer was active while the copy was taken, so it is consistent.
+                return f(unsafe { copy.assume_init_ref() });
+            }
+        }
+    }
+
+    /// Returns a consistent snapshot of the protected data.
+    pub fn get(&self) -> T {
+        self.read(|data| *data)
+    }
+}
+
+/// A kernel `seqlock_t` lock backend.
+///
+/// Locking it gives exclusive access to writers.
+pub struct SeqLockBackend;
+
+// SAFETY: The underlying kernel `seqlock_t` object ensures mutual exclusion among writers, and
+// readers only access copies that are validated against the sequence count. `relock` uses the
+// default implementation that always calls the same locking method.
+unsafe impl super::Backend for SeqLockBackend {
+    type State = bindings::seqlock_t;
+    type GuardState = ();
+
+    unsafe fn init(
+        ptr: *mut Self::State,
+        name: *const core::ffi::c_char,
+        key: *mut bindings::lock_class_key,
+    ) {
+        // SAFETY: The safety requirements ensure that `ptr` is valid for writes, and `name` and
+        // `key` are valid for read indefinitely.
+        unsafe { bindings::__seqlock_init(ptr, name, key) }
+    }
+
+    unsafe fn lock(ptr: *mut Self::State) -> Self::GuardState {
+        // SAFETY: The safety requirements of this function ensure that `ptr` points to valid
+        // memory, and that it has been initialised before.
+        unsafe { bindings::write_seqlock(ptr) }
+    }
+
+    unsafe fn try_lock(ptr: *mut Self::State) -> Option<Self::GuardState> {
+        // SAFETY: The safety requirements of this function ensure that `ptr` points to valid
+        // memory, and that it has been initialised before.
+        let acquired = unsafe { bindings::write_tryseqlock(ptr) } != 0;
+        acquired.then_some(())
+    }
+
+    unsafe fn unlock(ptr: *mut Self::State, _guard_state: &Self::GuardState) {
+        // SAFETY: The safety requirements of this function ensure that `ptr` is valid and that the
+        // caller is the owner of the seqlock.
+        unsafe { bindings::write_sequnlock(ptr) }
+    }
+
+    unsafe fn assert_is_held(ptr: *mut Self::State) {
+        // SAFETY: The safety requirements of this function ensure that `ptr` points to valid
+        // memory, and that it has been initialised before.
+        unsafe { bindings::seqlock_assert_is_held(ptr) }
+    }
+}
@@ -109,9 +109,216 @@ unsafe impl super::Backend for SpinLockBackend {
         unsafe { bindings::spin_lock(ptr) }
     }
 
+    unsafe fn try_lock(ptr: *mut Self::State) -> Option<Self::GuardState> {
+        // SAFETY: The safety requirements of this function ensure that `ptr` points to valid
+        // memory, and that it has been initialised before.
+        let acquired = unsafe { bindings::spin_trylock(ptr) } != 0;
+        acquired.then_some(())
+    }
+
     unsafe fn unlock(ptr: *mut Self::State, _guard_state: &Self::GuardState) {
         // SAFETY: The safety requirements of this function ensure that `ptr` is valid and that the
         // caller is the owner of the spinlock.
         unsafe { bindings::spin_unlock(ptr) }
     }
+
+    unsafe fn assert_is_held(ptr: *mut Self::State) {
+        // SAFETY: The safety requirements of this function ensure that `ptr` points to valid
+        // memory, and that it has been initialised before.
+        unsafe { bindings::spin_assert_is_held(ptr) }
+    }
+}
+
+/// Creates a [`SpinLockIrq`] initialiser with the given name and a newly-created lock class.
+///
+/// It uses the name if one is given, otherwise it generates one based on the file name and line
+/// number.
+#[macro_export]
+macro_rules! new_spinlock_irq {
+    ($inner:expr $(, $name:literal)? $(,)?) => {
+        $crate::sync::SpinLockIrq::new(
+            $inner, $crate::optional_name!($($name)?), $crate::static_lock_class!())
+    };
+}
+pub use new_spinlock_irq;
+
+/// A spinlock that may be acquired when local interrupts are disabled.
+///
+/// It is the same as [`SpinLock`], except that local interrupts are disabled while it is held
+/// (with `spin_lock_irqsave`), so it can protect data that is also accessed from hard interrupt
+/// handlers. The previous interrupt state is kept in the guard and restored when it is dropped, so
+/// guards must be dropped in the reverse order of their creation.
+///
+/// Instances of [`SpinLockIrq`] need a lock class and to be pinned. The recommended way to create
+/// such instances is with the [`pin_init`](crate::pin_init) and [`new_spinlock_irq`] macros.
+///
+/// # Examples
+///
+/// The following example shows how a counter shared with an interrupt handler may be updated from
+/// process context, and how [`CondVar`] may be used with it:
+///
+/// ```
+/// use kernel::sync::{new_condvar, new_spinlock_irq, CondVar, SpinLockIrq};
+///
+/// #[pin_data]
+/// struct Events {
+///     #[pin]
+///     pending: SpinLockIrq<u32>,
+///     #[pin]
+///     cond: CondVar,
+/// }
+///
+/// impl Events {
+///     // Called from the interrupt handler.
+///     fn raise(&self) {
+///         *self.pending.lock() += 1;
+///         self.cond.notify_one();
+///     }
+///
+///     fn wait_for_event(&self) -> u32 {
+///         let mut guard = self.pending.lock();
+///         while *guard == 0 {
+///             self.cond.wait(&mut guard);
+///         }
+///         core::mem::replace(&mut *guard, 0)
+///     }
+/// }
+///
+/// let events = Box::pin_init(pin_init!(Events {
+///     pending <- new_spinlock_irq!(0),
+///     cond <- new_condvar!(),
+/// }), GFP_KERNEL)?;
+///
+/// events.raise();
+/// assert_eq!(events.wait_for_event(), 1);
+/// # Ok::<(), Error>(())
+/// ```
+///
+/// [`CondVar`]: crate::sync::CondVar
+pub type SpinLockIrq<T> = super::Lock<T, SpinLockIrqBackend>;
+
+/// A kernel `spinlock_t` lock backend that disables local interrupts while the lock is held.
+pub struct SpinLockIrqBackend;
+
+// SAFETY: The underlying kernel `spinlock_t` object ensures mutual exclusion. `relock` uses the
+// default implementation that always calls the same locking method.
+unsafe impl super::Backend for SpinLockIrqBackend {
+    type State = bindings::spinlock_t;
+    type GuardState = core::ffi::c_ulong;
+
+    unsafe fn init(
+        ptr: *mut Self::State,
+        name: *const core::ffi::c_char,
+        key: *mut bindings::lock_class_key,
+    ) {
+        // SAFETY: The safety requirements ensure that `ptr` is valid for writes, and `name` and
+        // `key` are valid for read indefinitely.
+        unsafe { bindings::__spin_lock_init(ptr, name, key) }
+    }
+
+    unsafe fn lock(ptr: *mut Self::State) -> Self::GuardState {
+        // SAFETY: The safety requirements of this function ensure that `ptr` points to valid
+        // memory, and that it has been initialised before.
+        unsafe { bindings::spin_lock_irqsave(ptr) }
+    }
+
+    unsafe fn try_lock(ptr: *mut Self::State) -> Option<Self::GuardState> {
+        let mut flags = 0;
+        // SAFETY: The safety requirements of this function ensure that `ptr` points to valid
+        // memory, and that it has been initialised before. `flags` is valid for writes.
+        let acquired = unsafe { bindings::spin_trylock_irqsave(ptr, &mut flags) } != 0;
+        acquired.then_some(flags)
+    }
+
+    unsafe fn unlock(ptr: *mut Self::State, guard_state: &Self::GuardState) {
+        // SAFETY: The safety requirements of this function ensure that `ptr` is valid and that the
+        // caller is the owner of the spinlock. `guard_state` holds the interrupt state saved when
+        // the lock was acquired.
+        unsafe { bindings::spin_unlock_irqrestore(ptr, *guard_state) }
+    }
+
+    unsafe fn assert_is_held(ptr: *mut Self::State) {
+        // SAFETY: The safety requirements of this function ensure that `ptr` points to valid
+        // memory, and that it has been initialised before.
+        unsafe { bindings::spin_assert_is_held(ptr) }
+    }
+}
+
+/// Creates a [`SpinLockBh`] initialiser with the given name and a newly-created lock class.
+///
+/// It uses the name if one is given, otherwise it generates one based on the file name and line
+/// number.
+#[macro_export]
+macro_rules! new_spinlock_bh {
+    ($inner:expr $(, $name:literal)? $(,)?) => {
+        $crate::sync::SpinLockBh::new(
+            $inner, $crate::optional_name!($($name)?), $crate::static_lock_class!())
+    };
+}
+pub use new_spinlock_bh;
+
+/// A spinlock that disables bottom halves while it is held.
+///
+/// It is the same as [`SpinLock`], except that softirqs are disabled on the local CPU while it is
+/// held (with `spin_lock_bh`), so it can protect data that is also accessed from softirq context,
+/// e.g., timers or tasklets.
+///
+/// Instances of [`SpinLockBh`] need a lock class and to be pinned. The recommended way to create
+/// such instances is with the [`pin_init`](crate::pin_init) and [`new_spinlock_bh`] macros.
+///
+/// # Examples
+///
+/// ```
+/// use kernel::sync::{new_spinlock_bh, SpinLockBh};
+///
+/// let queue = Box::pin_init(new_spinlock_bh!(Vec::new()), GFP_KERNEL)?;
+/// queue.lock().push(42u32, GFP_ATOMIC)?;
+/// assert_eq!(queue.lock().pop(), Some(42));
+/// # Ok::<(), Error>(())
+/// ```
+pub type SpinLockBh<T> = super::Lock<T, SpinLockBhBackend>;
+
+/// A kernel `spinlock_t` lock backend that disables bottom halves while the lock is held.
+pub struct SpinLockBhBackend;
+
+// SAFETY: The underlying kernel `spinlock_t` object ensures mutual exclusion. `relock` uses the
+// default implementation that always calls the same locking method.
+unsafe impl super::Backend for SpinLockBhBackend {
+    type State = bindings::spinlock_t;
+    type GuardState = ();
+
+    unsafe fn init(
+        ptr: *mut Self::State,
+        name: *const core::ffi::c_char,
+        key: *mut bindings::lock_class_key,
+    ) {
+        // SAFETY: The safety requirements ensure that `ptr` is valid for writes, and `name` and
+        // `key` are valid for read indefinitely.
+        unsafe { bindings::__spin_lock_init(ptr, name, key) }
+    }
+
+    unsafe fn lock(ptr: *mut Self::State) -> Self::GuardState {
+        // SAFETY: The safety requirements of this function ensure that `ptr` points to valid
+        // memory, and that it has been initialised before.
+        unsafe { bindings::spin_lock_bh(ptr) }
+    }
+
+    unsafe fn try_lock(ptr: *mut Self::State) -> Option<Self::GuardState> {
+        // SAFETY: The safety requirements of this function ensure that `ptr` points to valid
+        // memory, and that it has been initialised before.
+        let acquired = unsafe { bindings::spin_trylock_bh(ptr) } != 0;
+        acquired.then_some(())
+    }
+
+    unsafe fn unlock(ptr: *mut Self::State, _guard_state: &Self::GuardState) {
+        // SAFETY: The safety requirements of this function ensure that `ptr` is valid and that the
+        // caller is the owner of the spinlock.
+        unsafe { bindings::spin_unlock_bh(ptr) }
+    }
+
+    unsafe fn assert_is_held(ptr: *mut Self::State) {
+        // SAFETY: The safety requirements of this function ensure that `ptr` points to valid
+        // memory, and that it has been initialised before.
+        unsafe { bindings::spin_assert_is_held(ptr) }
+    }
 }
@@ -2,7 +2,7 @@
 
 //! A wrapper for data protected by a lock that does not wrap it.
 
-use super::{lock::Backend, lock::Lock};
+use super::{lock::Backend, lock::Held, lock::Lock};
 use crate::build_assert;
 use core::{cell::UnsafeCell, mem::size_of, ptr};
 
@@ -29,6 +29,7 @@ use core::{cell::UnsafeCell, mem::size_of, ptr};
 /// locked; we enforce at run time that the right `InnerDirectory` is locked.
 ///
 /// ```
+/// use kernel::sync::lock::{mutex::MutexBackend, Held};
 /// use kernel::sync::{LockedBy, Mutex};
 ///
 /// struct InnerFile {
@@ -67,6 +68,11 @@ use core::{cell::UnsafeCell, mem::size_of, ptr};
 ///     file_inner.bytes_used += 10;
 /// }
 ///
+/// /// Returns `bytes_used` for the file, given a proof that the directory is locked.
+/// fn file_bytes_used(held: &Held<InnerDirectory, MutexBackend>, file: &File) -> u64 {
+///     file.inner.access_held(held).bytes_used
+/// }
+///
 /// /// Creates a new file.
 /// fn new_file(ino: u32, dir: &Directory) -> File {
 ///     File {
@@ -166,4 +172,55 @@ impl<T: ?Sized, U> LockedBy<T, U> {
         // SAFETY: `owner` is evidence that there is only one reference to the owner.
         unsafe { &mut *self.data.get() }
     }
+
+    /// Returns a reference to the protected data when the caller provides a proof that the owner
+    /// is locked.
+    ///
+    /// It is the same as [`LockedBy::access`], except that the proof does not give access to the
+    /// data protected by the owner.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `owner` is not a proof for the lock used in [`new`](LockedBy::new).
+    pub fn access_held<'a, B: Backend>(&'a self, owner: &'a Held<U, B>) -> &'a T
+    where
+        T: Sync,
+    {
+        build_assert!(
+            size_of::<U>() > 0,
+            "`U` cannot be a ZST because `owner` wouldn't be unique"
+        );
+        if !ptr::eq(owner as *const Held<U, B> as *const U, self.owner) {
+            panic!("mismatched owners");
+        }
+
+        // SAFETY: `owner` is evidence that the owner is locked for the duration of 'a, and the only
+        // way to obtain a mutable reference to the inner value is `Self::access_mut` or
+        // `Self::access_held_mut`, which need the guard to be mutably borrowed. The type is `Sync`
+        // so there are no other requirements.
+        unsafe { &*self.data.get() }
+    }
+
+    /// Returns a mutable reference to the protected data when the caller provides an exclusive
+    /// proof that the owner is locked.
+    ///
+    /// It is the same as [`LockedBy::access_mut`], except that the proof does not give access to
+    /// the data protected by the owner.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `owner` is not a proof for the lock used in [`new`](LockedBy::new).
+    pub fn access_held_mut<'a, B: Backend>(&'a self, owner: &'a mut Held<U, B>) -> &'a mut T {
+        build_assert!(
+            size_of::<U>() > 0,
+            "`U` cannot be a ZST because `owner` wouldn't be unique"
+        );
+        if !ptr::eq(owner as *const Held<U, B> as *const U, self.owner) {
+            panic!("mismatched owners");
+        }
+
+        // SAFETY: `owner` is evidence that the owner is locked and that the guard is mutably
+        // borrowed for the duration of 'a, so there are no other references to the inner value.
+        unsafe { &mut *self.data.get() }
+    }
 }
@@ -0,0 +1,93 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Utilities for file `poll` implementations.
+//!
+//! C header: [`include/linux/poll.h`](srctree/include/linux/poll.h)
+
+use super::WaitQueue;
+use crate::bindings;
+use core::{marker::PhantomData, sync::atomic::Ordering};
+
+/// A table that the wait queues of a polled file are registered in.
+///
+/// Wraps the kernel's [`struct poll_table_struct`] as passed to the `poll` file operation, along
+/// with the file being polled.
+///
+/// # Invariants
+///
+/// `file` is valid, and `table` is either null or valid, for the lifetime `'a`.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::{AtomicBool, Ordering};
+/// use kernel::sync::{poll::PollTable, WaitQueue};
+///
+/// #[pin_data]
+/// struct Device {
+///     readable: AtomicBool,
+///     #[pin]
+///     wait: WaitQueue,
+/// }
+///
+/// impl Device {
+///     // Called from the `poll` file operation.
+///     fn poll(&self, table: &mut PollTable<'_>) -> u32 {
+///         table.register_wait(&self.wait);
+///         if self.readable.load(Ordering::Acquire) {
+///             kernel::bindings::POLLIN | kernel::bindings::POLLRDNORM
+///         } else {
+///             0
+///         }
+///     }
+///
+///     // Called when data becomes available.
+///     fn data_ready(&self) {
+///         self.readable.store(true, Ordering::Release);
+///         self.wait.wake_up_all();
+///     }
+/// }
+/// ```
+///
+/// [`struct poll_table_struct`]: srctree/include/linux/poll.h
+pub struct PollTable<'a> {
+    file: *mut bindings::file,
+    table: *mut bindings::poll_table_struct,
+    _p: PhantomData<&'a mut bindings::poll_table_struct>,
+}
+
+impl<'a> PollTable<'a> {
+    /// Creates a [`PollTable`] from the arguments of a `poll` file operation.
+    ///
+    /// # Safety
+    ///
+    /// `file` must be valid, and `table` must be either null or valid, for the lifetime `'a`. It
+    /// is the case for the arguments of a `poll` file operation while it runs.
+    pub unsafe fn from_raw(
+        file: *mut bindings::file,
+        table: *mut bindings::poll_table_struct,
+    ) -> Self {
+        // INVARIANT: The safety requirements ensure that both pointers are valid for `'a`.
+        Self {
+            file,
+            table,
+            _p: PhantomData,
+        }
+    }
+
+    /// Registers `queue` in the table, so that [`WaitQueue::wake_up`] and
+    /// [`WaitQueue::wake_up_all`] wake up the poller.
+    ///
+    /// The registration may outlive the `poll` call (for example, with `epoll`), in which case it
+    /// is removed when `queue` is dropped.
+    pub fn register_wait(&mut self, queue: &WaitQueue) {
+        // Tells the destructor of `queue` to remove the entries of the pollers. The destructor has
+        // exclusive access, so relaxed ordering is enough.
+        queue.polled.store(true, Ordering::Relaxed);
+
+        // SAFETY: By the type invariants, `file` and `table` are valid (or `table` is null, which
+        // `poll_wait` handles). `wait_queue_head` was initialised by the constructor of `queue`,
+        // which is pinned and removes the registrations of the pollers when dropped.
+        unsafe { bindings::poll_wait(self.file, queue.wait_queue_head.get(), self.table) };
+    }
+}
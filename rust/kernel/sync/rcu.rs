@@ -0,0 +1,228 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Read-copy-update (RCU) support.
+//!
+//! C header: [`include/linux/rcupdate.h`](srctree/include/linux/rcupdate.h)
+
+use crate::{
+    alloc::{box_ext::BoxExt, AllocError, Flags},
+    bindings,
+    types::{ForeignOwnable, Opaque},
+};
+use core::{
+    ffi::c_void,
+    marker::PhantomData,
+    ptr,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+/// Evidence that the RCU read side lock is held on the current thread/CPU.
+///
+/// The type is explicitly not `Send` because this property is per-thread/CPU.
+///
+/// # Invariants
+///
+/// The RCU read side lock is actually held while instances of this guard exist.
+pub struct Guard {
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl Guard {
+    /// Acquires the RCU read side lock and returns a guard.
+    pub fn new() -> Self {
+        // SAFETY: An FFI call with no additional requirements.
+        unsafe { bindings::rcu_read_lock() };
+        // INVARIANT: The RCU read side lock was just acquired above.
+        Self {
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Explicitly releases the RCU read side lock.
+    pub fn unlock(self) {}
+}
+
+impl Default for Guard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, the RCU read side lock is held, so it is ok to unlock it.
+        unsafe { bindings::rcu_read_unlock() };
+    }
+}
+
+/// Acquires the RCU read side lock.
+pub fn read_lock() -> Guard {
+    Guard::new()
+}
+
+/// Waits for a full RCU grace period to elapse.
+///
+/// All RCU read side critical sections that were in progress when this function was called are
+/// guaranteed to have completed when it returns. It may sleep, so it must not be called while
+/// holding a [`Guard`] or in atomic context.
+pub fn synchronize_rcu() {
+    // SAFETY: An FFI call with no additional requirements.
+    unsafe { bindings::synchronize_rcu() };
+}
+
+/// A pointer to an object that readers access under the RCU read side lock.
+///
+/// Readers borrow the current object with [`RcuPtr::get`], which ties the borrow to a [`Guard`].
+/// Writers publish a new object with [`RcuPtr::replace`] or [`RcuPtr::replace_deferred`], and the
+/// previous object is only dropped once all readers that could still see it are gone.
+///
+/// # Invariants
+///
+/// `ptr` is either null or a pointer returned by [`ForeignOwnable::into_foreign`] for which
+/// [`ForeignOwnable::from_foreign`] hasn't been called yet. The object it points to is owned by
+/// the [`RcuPtr`].
+///
+/// # Examples
+///
+/// ```
+/// use kernel::sync::rcu::{self, RcuPtr};
+///
+/// struct Route {
+///     dest: u32,
+///     gateway: u32,
+/// }
+///
+/// let route = RcuPtr::new(Some(Box::new(Route { dest: 1, gateway: 10 }, GFP_KERNEL)?));
+///
+/// let guard = rcu::read_lock();
+/// let old = route.get(&guard).unwrap();
+/// assert_eq!(old.gateway, 10);
+///
+/// // Readers that already hold a reference keep seeing the old object.
+/// let new = Box::new(Route { dest: 1, gateway: 20 }, GFP_KERNEL)?;
+/// route.replace_deferred(Some(new), GFP_KERNEL)?;
+/// assert_eq!(old.gateway, 10);
+/// assert_eq!(route.get(&guard).unwrap().gateway, 20);
+/// drop(guard);
+///
+/// // Waits for a grace period and gives the previous object back.
+/// let prev = route.replace(None).unwrap();
+/// assert_eq!((prev.dest, prev.gateway), (1, 20));
+/// assert!(route.get(&rcu::read_lock()).is_none());
+/// # Ok::<(), Error>(())
+/// ```
+pub struct RcuPtr<P: ForeignOwnable> {
+    ptr: AtomicPtr<c_void>,
+    _p: PhantomData<P>,
+}
+
+// SAFETY: The object is owned by the `RcuPtr`, so it can be transferred across thread boundaries
+// if `P` can.
+unsafe impl<P: ForeignOwnable + Send> Send for RcuPtr<P> {}
+
+// SAFETY: Shared references allow borrowing the object concurrently from any thread (so `P` must
+// be `Sync`), and replacing it, which may drop it on another thread (so `P` must be `Send`).
+unsafe impl<P: ForeignOwnable + Send + Sync> Sync for RcuPtr<P> {}
+
+impl<P: ForeignOwnable> RcuPtr<P> {
+    /// Creates a new RCU-protected pointer, optionally holding an initial object.
+    pub fn new(value: Option<P>) -> Self {
+        // INVARIANT: The pointer is either null or was just returned by `into_foreign`.
+        Self {
+            ptr: AtomicPtr::new(Self::to_foreign(value)),
+            _p: PhantomData,
+        }
+    }
+
+    fn to_foreign(value: Option<P>) -> *mut c_void {
+        value.map_or(ptr::null_mut(), |v| v.into_foreign() as *mut c_void)
+    }
+
+    /// Borrows the current object, if any (`rcu_dereference`).
+    ///
+    /// The borrow cannot outlive the RCU read side critical section of `guard`.
+    pub fn get<'a>(&'a self, _guard: &'a Guard) -> Option<P::Borrowed<'a>> {
+        // The acquire ordering pairs with the release ordering of the writers, so the object is
+        // seen fully initialised.
+        let ptr = self.ptr.load(Ordering::Acquire);
+        if ptr.is_null() {
+            return None;
+        }
+
+        // SAFETY: By the type invariants, `ptr` came from `into_foreign`. Writers only convert it
+        // back with `from_foreign` after a grace period, which cannot elapse while `_guard` exists.
+        Some(unsafe { P::borrow(ptr) })
+    }
+
+    /// Publishes `new` (`rcu_assign_pointer`), waits for a grace period and returns the previous
+    /// object.
+    ///
+    /// Since it calls [`synchronize_rcu`], it may sleep and must not be called while holding a
+    /// [`Guard`].
+    pub fn replace(&self, new: Option<P>) -> Option<P> {
+        let old = self.ptr.swap(Self::to_foreign(new), Ordering::AcqRel);
+        synchronize_rcu();
+
+        // SAFETY: By the type invariants, `old` is either null or came from `into_foreign`. It was
+        // unpublished above and a grace period has elapsed since, so no borrows remain.
+        unsafe { P::try_from_foreign(old) }
+    }
+
+    /// Publishes `new` (`rcu_assign_pointer`) and drops the previous object after a grace period,
+    /// without waiting for it (`call_rcu`).
+    ///
+    /// The previous object is dropped from softirq context, so dropping `P` must not sleep.
+    ///
+    /// On allocation failure, the current object is left in place and `new` is dropped.
+    pub fn replace_deferred(&self, new: Option<P>, flags: Flags) -> Result<(), AllocError>
+    where
+        P: Send,
+    {
+        let slot = <Box<RcuDrop<P>> as BoxExt<_>>::new_uninit(flags)?;
+
+        let old = self.ptr.swap(Self::to_foreign(new), Ordering::AcqRel);
+        // SAFETY: By the type invariants, `old` is either null or came from `into_foreign`. It was
+        // just unpublished, and ownership of the object is transferred to the callback below,
+        // which only drops it after a grace period.
+        let Some(value) = (unsafe { P::try_from_foreign(old) }) else {
+            return Ok(());
+        };
+
+        let rcu = Box::into_raw(Box::write(
+            slot,
+            RcuDrop {
+                head: Opaque::uninit(),
+                value,
+            },
+        ));
+
+        // SAFETY: `rcu` is valid and was just allocated, so `head` is not queued yet. The callback
+        // takes ownership of the allocation.
+        unsafe { bindings::call_rcu((*rcu).head.get(), Some(RcuDrop::<P>::callback)) };
+        Ok(())
+    }
+}
+
+impl<P: ForeignOwnable> Drop for RcuPtr<P> {
+    fn drop(&mut self) {
+        let ptr = *self.ptr.get_mut();
+        // SAFETY: By the type invariants, `ptr` is either null or came from `into_foreign`. Readers
+        // borrow `self`, so there are none left.
+        drop(unsafe { P::try_from_foreign(ptr) });
+    }
+}
+
+/// An object whose drop is deferred until after an RCU grace period.
+#[repr(C)]
+struct RcuDrop<P> {
+    head: Opaque<bindings::callback_head>,
+    value: P,
+}
+
+impl<P> RcuDrop<P> {
+    unsafe extern "C" fn callback(head: *mut bindings::callback_head) {
+        // SAFETY: `head` is the first field of a `repr(C)` `RcuDrop<P>` allocated with `Box` in
+        // `RcuPtr::replace_deferred`, whose ownership was transferred to this callback.
+        drop(unsafe { Box::from_raw(head.cast::<RcuDrop<P>>()) });
+    }
+}
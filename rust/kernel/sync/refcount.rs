@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! A reference count.
+//!
+//! C header: [`include/linux/refcount.h`](srctree/include/linux/refcount.h)
+
+use crate::{bindings, types::Opaque};
+
+/// A reference count with saturation semantics.
+///
+/// Exposes the kernel's [`refcount_t`]. Instead of wrapping around, the count saturates (and
+/// warns) on overflow and on underflow, so a reference counting bug leaks the object instead of
+/// leading to a use-after-free. Once saturated, the count never changes again.
+///
+/// # Examples
+///
+/// ```
+/// use kernel::sync::Refcount;
+///
+/// let count = Refcount::new(1);
+/// count.inc();
+/// assert_eq!(count.read(), 2);
+///
+/// assert!(!count.dec_and_test());
+/// assert!(count.dec_and_test());
+///
+/// // Objects whose count reached zero are being destroyed and cannot be revived.
+/// assert!(!count.inc_not_zero());
+/// assert_eq!(count.read(), 0);
+/// ```
+///
+/// [`refcount_t`]: srctree/include/linux/refcount_types.h
+#[repr(transparent)]
+pub struct Refcount(Opaque<bindings::refcount_t>);
+
+// SAFETY: `refcount_t` is only accessed atomically, so it is safe to use on any thread.
+unsafe impl Send for Refcount {}
+
+// SAFETY: `refcount_t` is only accessed atomically, so it is safe to use on multiple threads
+// concurrently.
+unsafe impl Sync for Refcount {}
+
+impl Refcount {
+    /// Creates a new reference count with the given value.
+    ///
+    /// Negative values are considered saturated.
+    pub fn new(value: i32) -> Self {
+        // SAFETY: There are no safety requirements for this FFI call.
+        Self(Opaque::new(unsafe { bindings::REFCOUNT_INIT(value) }))
+    }
+
+    /// Returns a raw pointer to the underlying `refcount_t`.
+    pub fn as_ptr(&self) -> *mut bindings::refcount_t {
+        self.0.get()
+    }
+
+    /// Returns the current value.
+    ///
+    /// The value may change concurrently, so it is mostly useful for diagnostics, or when the
+    /// caller knows that it cannot change.
+    pub fn read(&self) -> i32 {
+        // SAFETY: `as_ptr` points to a valid `refcount_t`.
+        unsafe { bindings::refcount_read(self.as_ptr()) as i32 }
+    }
+
+    /// Sets the value.
+    pub fn set(&self, value: i32) {
+        // SAFETY: `as_ptr` points to a valid `refcount_t`.
+        unsafe { bindings::refcount_set(self.as_ptr(), value) }
+    }
+
+    /// Increments the count.
+    ///
+    /// Incrementing a count of zero is a bug: it warns and saturates the count, see
+    /// [`Refcount::inc_not_zero`] for objects that may concurrently be destroyed.
+    pub fn inc(&self) {
+        // SAFETY: `as_ptr` points to a valid `refcount_t`.
+        unsafe { bindings::refcount_inc(self.as_ptr()) }
+    }
+
+    /// Increments the count unless it is zero.
+    ///
+    /// Returns whether the count was incremented. It has control dependency ordering, so the
+    /// object can be used after a successful increment.
+    #[must_use = "the count is only incremented if it returns true"]
+    pub fn inc_not_zero(&self) -> bool {
+        // SAFETY: `as_ptr` points to a valid `refcount_t`.
+        unsafe { bindings::refcount_inc_not_zero(self.as_ptr()) }
+    }
+
+    /// Decrements the count.
+    ///
+    /// The count must not reach zero, otherwise it warns and the object is leaked; use
+    /// [`Refcount::dec_and_test`] to release the last reference.
+    pub fn dec(&self) {
+        // SAFETY: `as_ptr` points to a valid `refcount_t`.
+        unsafe { bindings::refcount_dec(self.as_ptr()) }
+    }
+
+    /// Decrements the count and returns whether it reached zero.
+    ///
+    /// It has release ordering, and acquire ordering when it returns `true`, so the object can be
+    /// safely destroyed in that case.
+    #[must_use = "the object must be destroyed when the count reaches zero"]
+    pub fn dec_and_test(&self) -> bool {
+        // SAFETY: `as_ptr` points to a valid `refcount_t`.
+        unsafe { bindings::refcount_dec_and_test(self.as_ptr()) }
+    }
+}
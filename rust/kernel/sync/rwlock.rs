@@ -0,0 +1,315 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Generic kernel reader-writer lock and guards.
+//!
+//! It contains a generic Rust reader-writer lock and guards that allow for different backends
+//! (e.g., read-write semaphores, spinning reader-writer locks) to be provided with minimal effort.
+//!
+//! A reader-writer lock allows either many readers or a single writer to access the protected
+//! data at a time, which suits read-mostly data better than a [`Mutex`](super::Mutex).
+
+use super::LockClassKey;
+use crate::{init::PinInit, pin_init, str::CStr, types::Opaque};
+use core::{cell::UnsafeCell, marker::PhantomData, marker::PhantomPinned};
+use macros::pin_data;
+
+pub mod semaphore;
+pub mod spinlock;
+
+/// The "backend" of a reader-writer lock.
+///
+/// It is the actual implementation of the lock, without the need to repeat patterns used in all
+/// reader-writer locks.
+///
+/// # Safety
+///
+/// Implementers must ensure that, once the lock is initialised, either any number of threads/CPUs
+/// own it for reading (between calls to [`read_lock`] and [`read_unlock`]), or a single thread/CPU
+/// owns it for writing (between calls to [`write_lock`] and [`write_unlock`]), but not both. The
+/// `try_` variants must only return `true` when they acquired the lock in the same way.
+///
+/// [`read_lock`]: RwBackend::read_lock
+/// [`read_unlock`]: RwBackend::read_unlock
+/// [`write_lock`]: RwBackend::write_lock
+/// [`write_unlock`]: RwBackend::write_unlock
+pub unsafe trait RwBackend {
+    /// The state required by the lock.
+    type State;
+
+    /// Initialises the lock.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for write for the duration of the call, while `name` and `key` must
+    /// remain valid for read indefinitely.
+    unsafe fn init(
+        ptr: *mut Self::State,
+        name: *const core::ffi::c_char,
+        key: *mut bindings::lock_class_key,
+    );
+
+    /// Acquires the lock for reading.
+    ///
+    /// # Safety
+    ///
+    /// Callers must ensure that [`RwBackend::init`] has been previously called.
+    unsafe fn read_lock(ptr: *mut Self::State);
+
+    /// Tries to acquire the lock for reading, returns whether it succeeded.
+    ///
+    /// # Safety
+    ///
+    /// Callers must ensure that [`RwBackend::init`] has been previously called.
+    unsafe fn try_read_lock(ptr: *mut Self::State) -> bool;
+
+    /// Releases the lock after it was acquired for reading.
+    ///
+    /// # Safety
+    ///
+    /// It must only be called by a current reader of the lock.
+    unsafe fn read_unlock(ptr: *mut Self::State);
+
+    /// Acquires the lock for writing.
+    ///
+    /// # Safety
+    ///
+    /// Callers must ensure that [`RwBackend::init`] has been previously called.
+    unsafe fn write_lock(ptr: *mut Self::State);
+
+    /// Tries to acquire the lock for writing, returns whether it succeeded.
+    ///
+    /// # Safety
+    ///
+    /// Callers must ensure that [`RwBackend::init`] has been previously called.
+    unsafe fn try_write_lock(ptr: *mut Self::State) -> bool;
+
+    /// Releases the lock after it was acquired for writing.
+    ///
+    /// # Safety
+    ///
+    /// It must only be called by the current writer of the lock.
+    unsafe fn write_unlock(ptr: *mut Self::State);
+}
+
+/// A reader-writer lock backend that can atomically turn a writer into a reader.
+///
+/// # Safety
+///
+/// Implementers must ensure that [`downgrade`] turns the ownership of the writer into read
+/// ownership without letting any other writer acquire the lock in between.
+///
+/// [`downgrade`]: RwDowngradeBackend::downgrade
+pub unsafe trait RwDowngradeBackend: RwBackend {
+    /// Converts the write ownership of the caller into read ownership.
+    ///
+    /// # Safety
+    ///
+    /// It must only be called by the current writer of the lock.
+    unsafe fn downgrade(ptr: *mut Self::State);
+}
+
+/// A reader-writer lock.
+///
+/// Exposes one of the kernel reader-writer locking primitives. Which one is exposed depends on the
+/// lock [`RwBackend`] specified as the generic parameter `B`.
+#[pin_data]
+pub struct RwLock<T: ?Sized, B: RwBackend> {
+    /// The kernel lock object.
+    #[pin]
+    state: Opaque<B::State>,
+
+    /// Some locks are known to be self-referential (e.g., read-write semaphores), while others
+    /// are architecture or config defined (e.g., reader-writer spinlocks). So we conservatively
+    /// require them to be pinned in case some architecture uses self-references now or in the
+    /// future.
+    #[pin]
+    _pin: PhantomPinned,
+
+    /// The data protected by the lock.
+    data: UnsafeCell<T>,
+}
+
+// SAFETY: `RwLock` can be transferred across thread boundaries iff the data it protects can.
+unsafe impl<T: ?Sized + Send, B: RwBackend> Send for RwLock<T, B> {}
+
+// SAFETY: `RwLock` serialises the interior mutability it provides with respect to writers, so it
+// is `Sync` as long as the data it protects is `Send` (since writers get `&mut T`) and `Sync`
+// (since concurrent readers share `&T`).
+unsafe impl<T: ?Sized + Send + Sync, B: RwBackend> Sync for RwLock<T, B> {}
+
+impl<T, B: RwBackend> RwLock<T, B> {
+    /// Constructs a new reader-writer lock initialiser.
+    pub fn new(t: T, name: &'static CStr, key: &'static LockClassKey) -> impl PinInit<Self> {
+        pin_init!(Self {
+            data: UnsafeCell::new(t),
+            _pin: PhantomPinned,
+            // SAFETY: `slot` is valid while the closure is called and both `name` and `key` have
+            // static lifetimes so they live indefinitely.
+            state <- Opaque::ffi_init(|slot| unsafe {
+                B::init(slot, name.as_char_ptr(), key.as_ptr())
+            }),
+        })
+    }
+}
+
+impl<T: ?Sized, B: RwBackend> RwLock<T, B> {
+    /// Acquires the lock for reading and gives the caller shared access to the data protected by
+    /// it.
+    pub fn read(&self) -> ReadGuard<'_, T, B> {
+        // SAFETY: The constructor of the type calls `init`, so the existence of the object proves
+        // that `init` was called.
+        unsafe { B::read_lock(self.state.get()) };
+        // SAFETY: The lock was just acquired for reading.
+        unsafe { ReadGuard::new(self) }
+    }
+
+    /// Tries to acquire the lock for reading without waiting.
+    ///
+    /// Returns a guard that can be used to access the data protected by the lock if successful.
+    pub fn try_read(&self) -> Option<ReadGuard<'_, T, B>> {
+        // SAFETY: The constructor of the type calls `init`, so the existence of the object proves
+        // that `init` was called.
+        if unsafe { B::try_read_lock(self.state.get()) } {
+            // SAFETY: The lock was just acquired for reading.
+            Some(unsafe { ReadGuard::new(self) })
+        } else {
+            None
+        }
+    }
+
+    /// Acquires the lock for writing and gives the caller exclusive access to the data protected
+    /// by it.
+    pub fn write(&self) -> WriteGuard<'_, T, B> {
+        // SAFETY: The constructor of the type calls `init`, so the existence of the object proves
+        // that `init` was called.
+        unsafe { B::write_lock(self.state.get()) };
+        // SAFETY: The lock was just acquired for writing.
+        unsafe { WriteGuard::new(self) }
+    }
+
+    /// Tries to acquire the lock for writing without waiting.
+    ///
+    /// Returns a guard that can be used to access the data protected by the lock if successful.
+    pub fn try_write(&self) -> Option<WriteGuard<'_, T, B>> {
+        // SAFETY: The constructor of the type calls `init`, so the existence of the object proves
+        // that `init` was called.
+        if unsafe { B::try_write_lock(self.state.get()) } {
+            // SAFETY: The lock was just acquired for writing.
+            Some(unsafe { WriteGuard::new(self) })
+        } else {
+            None
+        }
+    }
+}
+
+/// A guard of a reader-writer lock acquired for reading.
+///
+/// It releases the read ownership of the lock when it goes out of scope, and gives shared access
+/// to the data protected by the lock in the meantime.
+#[must_use = "the lock unlocks immediately when the guard is unused"]
+pub struct ReadGuard<'a, T: ?Sized, B: RwBackend> {
+    lock: &'a RwLock<T, B>,
+    _not_send: PhantomData<*mut ()>,
+}
+
+// SAFETY: `ReadGuard` only gives out shared references, so it is sync when the data protected by
+// the lock is also sync.
+unsafe impl<T: Sync + ?Sized, B: RwBackend> Sync for ReadGuard<'_, T, B> {}
+
+impl<'a, T: ?Sized, B: RwBackend> ReadGuard<'a, T, B> {
+    /// Constructs a new read guard.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that it owns the lock for reading.
+    unsafe fn new(lock: &'a RwLock<T, B>) -> Self {
+        Self {
+            lock,
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized, B: RwBackend> core::ops::Deref for ReadGuard<'_, T, B> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: The caller owns the lock for reading, so there are no writers and it is safe to
+        // deref the protected data.
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized, B: RwBackend> Drop for ReadGuard<'_, T, B> {
+    fn drop(&mut self) {
+        // SAFETY: The caller owns the lock for reading, so it is safe to unlock it.
+        unsafe { B::read_unlock(self.lock.state.get()) };
+    }
+}
+
+/// A guard of a reader-writer lock acquired for writing.
+///
+/// It releases the write ownership of the lock when it goes out of scope, and gives exclusive
+/// access to the data protected by the lock in the meantime.
+#[must_use = "the lock unlocks immediately when the guard is unused"]
+pub struct WriteGuard<'a, T: ?Sized, B: RwBackend> {
+    lock: &'a RwLock<T, B>,
+    _not_send: PhantomData<*mut ()>,
+}
+
+// SAFETY: `WriteGuard` is sync when the data protected by the lock is also sync.
+unsafe impl<T: Sync + ?Sized, B: RwBackend> Sync for WriteGuard<'_, T, B> {}
+
+impl<'a, T: ?Sized, B: RwBackend> WriteGuard<'a, T, B> {
+    /// Constructs a new write guard.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that it owns the lock for writing.
+    unsafe fn new(lock: &'a RwLock<T, B>) -> Self {
+        Self {
+            lock,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Atomically converts the write ownership of the lock into read ownership.
+    ///
+    /// No other writer can acquire the lock in between, so the data observed through the returned
+    /// guard is the data left by this writer. Other readers may acquire the lock right away.
+    pub fn downgrade(self) -> ReadGuard<'a, T, B>
+    where
+        B: RwDowngradeBackend,
+    {
+        let lock = self.lock;
+        core::mem::forget(self);
+        // SAFETY: The caller owns the lock for writing, and the guard that would release it was
+        // just forgotten.
+        unsafe { B::downgrade(lock.state.get()) };
+        // SAFETY: The lock was just downgraded, so the caller owns it for reading.
+        unsafe { ReadGuard::new(lock) }
+    }
+}
+
+impl<T: ?Sized, B: RwBackend> core::ops::Deref for WriteGuard<'_, T, B> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: The caller owns the lock for writing, so it is safe to deref the protected data.
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized, B: RwBackend> core::ops::DerefMut for WriteGuard<'_, T, B> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: The caller owns the lock for writing, so it is safe to deref the protected data.
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized, B: RwBackend> Drop for WriteGuard<'_, T, B> {
+    fn drop(&mut self) {
+        // SAFETY: The caller owns the lock for writing, so it is safe to unlock it.
+        unsafe { B::write_unlock(self.lock.state.get()) };
+    }
+}
@@ -0,0 +1,146 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! A kernel read-write semaphore.
+//!
+//! This module allows Rust code to use the kernel's `struct rw_semaphore`.
+
+/// Creates a [`RwSemaphore`] initialiser with the given name and a newly-created lock class.
+///
+/// It uses the name if one is given, otherwise it generates one based on the file name and line
+/// number.
+#[macro_export]
+macro_rules! new_rwsem {
+    ($inner:expr $(, $name:literal)? $(,)?) => {
+        $crate::sync::RwSemaphore::new(
+            $inner, $crate::optional_name!($($name)?), $crate::static_lock_class!())
+    };
+}
+pub use new_rwsem;
+
+/// A sleeping reader-writer lock.
+///
+/// Exposes the kernel's [`struct rw_semaphore`]. Any number of readers may hold it at the same
+/// time, while a writer holds it exclusively. Threads that cannot acquire it block (sleep) until
+/// it is released.
+///
+/// Since it may block, [`RwSemaphore`] needs to be used with care in atomic contexts.
+///
+/// Instances of [`RwSemaphore`] need a lock class and to be pinned. The recommended way to create
+/// such instances is with the [`pin_init`](crate::pin_init) and [`new_rwsem`] macros.
+///
+/// # Examples
+///
+/// The following example shows a read-mostly configuration table that is updated by a writer
+/// which then keeps reading it without letting other writers in:
+///
+/// ```
+/// use kernel::sync::{new_rwsem, RwSemaphore};
+///
+/// struct Config {
+///     mtu: u32,
+///     retries: u32,
+/// }
+///
+/// #[pin_data]
+/// struct Device {
+///     #[pin]
+///     config: RwSemaphore<Config>,
+/// }
+///
+/// impl Device {
+///     fn new() -> impl PinInit<Self> {
+///         pin_init!(Self {
+///             config <- new_rwsem!(Config { mtu: 1500, retries: 3 }),
+///         })
+///     }
+///
+///     fn mtu(&self) -> u32 {
+///         self.config.read().mtu
+///     }
+///
+///     fn set_mtu(&self, mtu: u32) -> u32 {
+///         let mut guard = self.config.write();
+///         guard.mtu = mtu;
+///         let guard = guard.downgrade();
+///         guard.retries
+///     }
+/// }
+///
+/// let dev = Box::pin_init(Device::new(), GFP_KERNEL)?;
+/// assert_eq!(dev.mtu(), 1500);
+/// assert_eq!(dev.set_mtu(9000), 3);
+///
+/// let r1 = dev.config.read();
+/// let r2 = dev.config.try_read();
+/// assert!(r2.is_some());
+/// assert!(dev.config.try_write().is_none());
+/// assert_eq!(r1.mtu, 9000);
+/// # Ok::<(), Error>(())
+/// ```
+///
+/// [`struct rw_semaphore`]: srctree/include/linux/rwsem.h
+pub type RwSemaphore<T> = super::RwLock<T, RwSemaphoreBackend>;
+
+/// A kernel `struct rw_semaphore` lock backend.
+pub struct RwSemaphoreBackend;
+
+// SAFETY: The underlying kernel `struct rw_semaphore` object ensures that there is either a single
+// writer or any number of readers.
+unsafe impl super::RwBackend for RwSemaphoreBackend {
+    type State = bindings::rw_semaphore;
+
+    unsafe fn init(
+        ptr: *mut Self::State,
+        name: *const core::ffi::c_char,
+        key: *mut bindings::lock_class_key,
+    ) {
+        // SAFETY: The safety requirements ensure that `ptr` is valid for writes, and `name` and
+        // `key` are valid for read indefinitely.
+        unsafe { bindings::__init_rwsem(ptr, name, key) }
+    }
+
+    unsafe fn read_lock(ptr: *mut Self::State) {
+        // SAFETY: The safety requirements of this function ensure that `ptr` points to valid
+        // memory, and that it has been initialised before.
+        unsafe { bindings::down_read(ptr) }
+    }
+
+    unsafe fn try_read_lock(ptr: *mut Self::State) -> bool {
+        // SAFETY: The safety requirements of this function ensure that `ptr` points to valid
+        // memory, and that it has been initialised before.
+        unsafe { bindings::down_read_trylock(ptr) != 0 }
+    }
+
+    unsafe fn read_unlock(ptr: *mut Self::State) {
+        // SAFETY: The safety requirements of this function ensure that `ptr` is valid and that the
+        // caller is a reader of the semaphore.
+        unsafe { bindings::up_read(ptr) }
+    }
+
+    unsafe fn write_lock(ptr: *mut Self::State) {
+        // SAFETY: The safety requirements of this function ensure that `ptr` points to valid
+        // memory, and that it has been initialised before.
+        unsafe { bindings::down_write(ptr) }
+    }
+
+    unsafe fn try_write_lock(ptr: *mut Self::State) -> bool {
+        // SAFETY: The safety requirements of this function ensure that `ptr` points to valid
+        // memory, and that it has been initialised before.
+        unsafe { bindings::down_write_trylock(ptr) != 0 }
+    }
+
+    unsafe fn write_unlock(ptr: *mut Self::State) {
+        // SAFETY: The safety requirements of this function ensure that `ptr` is valid and that the
+        // caller is the writer of the semaphore.
+        unsafe { bindings::up_write(ptr) }
+    }
+}
+
+// SAFETY: `downgrade_write` atomically turns the writer into a reader.
+unsafe impl super::RwDowngradeBackend for RwSemaphoreBackend {
+    unsafe fn downgrade(ptr: *mut Self::State) {
+        // SAFETY: The safety requirements of this function ensure that `ptr` is valid and that the
+        // caller is the writer of the semaphore.
+        unsafe { bindings::downgrade_write(ptr) }
+    }
+}
@@ -0,0 +1,112 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! A kernel reader-writer spinlock.
+//!
+//! This module allows Rust code to use the kernel's `rwlock_t`.
+
+/// Creates a [`SpinRwLock`] initialiser with the given name and a newly-created lock class.
+///
+/// It uses the name if one is given, otherwise it generates one based on the file name and line
+/// number.
+#[macro_export]
+macro_rules! new_spin_rwlock {
+    ($inner:expr $(, $name:literal)? $(,)?) => {
+        $crate::sync::SpinRwLock::new(
+            $inner, $crate::optional_name!($($name)?), $crate::static_lock_class!())
+    };
+}
+pub use new_spin_rwlock;
+
+/// A spinning reader-writer lock.
+///
+/// Exposes the kernel's [`rwlock_t`]. Any number of readers may hold it at the same time, while a
+/// writer holds it exclusively. CPUs that cannot acquire it block (spinning) until it is released.
+///
+/// Note that `rwlock_t` favours readers, so writers may starve under constant read load. It also
+/// cannot be downgraded from a writer to a reader.
+///
+/// Instances of [`SpinRwLock`] need a lock class and to be pinned. The recommended way to create
+/// such instances is with the [`pin_init`](crate::pin_init) and [`new_spin_rwlock`] macros.
+///
+/// # Examples
+///
+/// ```
+/// use kernel::sync::{new_spin_rwlock, SpinRwLock};
+///
+/// #[pin_data]
+/// struct Table {
+///     #[pin]
+///     routes: SpinRwLock<[u32; 4]>,
+/// }
+///
+/// let table = Box::pin_init(pin_init!(Table {
+///     routes <- new_spin_rwlock!([0; 4]),
+/// }), GFP_KERNEL)?;
+///
+/// table.routes.write()[1] = 10;
+///
+/// let r1 = table.routes.read();
+/// let r2 = table.routes.read();
+/// assert_eq!(r1[1], 10);
+/// assert_eq!(r2[1], 10);
+/// assert!(table.routes.try_write().is_none());
+/// # Ok::<(), Error>(())
+/// ```
+///
+/// [`rwlock_t`]: srctree/include/linux/rwlock_types.h
+pub type SpinRwLock<T> = super::RwLock<T, SpinRwLockBackend>;
+
+/// A kernel `rwlock_t` lock backend.
+pub struct SpinRwLockBackend;
+
+// SAFETY: The underlying kernel `rwlock_t` object ensures that there is either a single writer or
+// any number of readers.
+unsafe impl super::RwBackend for SpinRwLockBackend {
+    type State = bindings::rwlock_t;
+
+    unsafe fn init(
+        ptr: *mut Self::State,
+        name: *const core::ffi::c_char,
+        key: *mut bindings::lock_class_key,
+    ) {
+        // SAFETY: The safety requirements ensure that `ptr` is valid for writes, and `name` and
+        // `key` are valid for read indefinitely.
+        unsafe { bindings::__rwlock_init(ptr, name, key) }
+    }
+
+    unsafe fn read_lock(ptr: *mut Self::State) {
+        // SAFETY: The safety requirements of this function ensure that `ptr` points to valid
+        // memory, and that it has been initialised before.
+        unsafe { bindings::read_lock(ptr) }
+    }
+
+    unsafe fn try_read_lock(ptr: *mut Self::State) -> bool {
+        // SAFETY: The safety requirements of this function ensure that `ptr` points to valid
+        // memory, and that it has been initialised before.
+        unsafe { bindings::read_trylock(ptr) != 0 }
+    }
+
+    unsafe fn read_unlock(ptr: *mut Self::State) {
+        // SAFETY: The safety requirements of this function ensure that `ptr` is valid and that the
+        // caller is a reader of the lock.
+        unsafe { bindings::read_unlock(ptr) }
+    }
+
+    unsafe fn write_lock(ptr: *mut Self::State) {
+        // SAFETY: The safety requirements of this function ensure that `ptr` points to valid
+        // memory, and that it has been initialised before.
+        unsafe { bindings::write_lock(ptr) }
+    }
+
+    unsafe fn try_write_lock(ptr: *mut Self::State) -> bool {
+        // SAFETY: The safety requirements of this function ensure that `ptr` points to valid
+        // memory, and that it has been initialised before.
+        unsafe { bindings::write_trylock(ptr) != 0 }
+    }
+
+    unsafe fn write_unlock(ptr: *mut Self::State) {
+        // SAFETY: The safety requirements of this function ensure that `ptr` is valid and that the
+        // caller is the writer of the lock.
+        unsafe { bindings::write_unlock(ptr) }
+    }
+}
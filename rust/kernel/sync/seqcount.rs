@@ -0,0 +1,211 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! A sequence counter for data whose writers are serialised by a lock that does not wrap it.
+//!
+//! C header: [`include/linux/seqlock.h`](srctree/include/linux/seqlock.h)
+
+use super::{lock::Backend, lock::Lock, LockClassKey};
+use crate::{build_assert, init::PinInit, pin_init, str::CStr, types::Opaque};
+use core::{
+    cell::UnsafeCell,
+    marker::PhantomData,
+    marker::PhantomPinned,
+    mem::{size_of, MaybeUninit},
+    ptr,
+};
+use macros::pin_data;
+
+/// Creates a [`SeqCount`] initialiser with the given owner, name and a newly-created lock class.
+///
+/// It uses the name if one is given, otherwise it generates one based on the file name and line
+/// number.
+#[macro_export]
+macro_rules! new_seqcount {
+    ($owner:expr, $inner:expr $(, $name:literal)? $(,)?) => {
+        $crate::sync::SeqCount::new(
+            $owner, $inner, $crate::optional_name!($($name)?), $crate::static_lock_class!())
+    };
+}
+pub use new_seqcount;
+
+/// Data that is read locklessly and written under a lock that does not wrap it.
+///
+/// Exposes the kernel's [`seqcount_t`]. Writers show evidence that the external lock is held, in
+/// the same way as with [`LockedBy`](super::LockedBy), while readers take a snapshot of the data
+/// and retry if a writer was active in the meantime, without ever taking the lock. Since readers
+/// may observe a torn copy that is then discarded, the data must be [`Copy`].
+///
+/// # Examples
+///
+/// ```
+/// use kernel::sync::{new_mutex, new_seqcount, Mutex, SeqCount};
+///
+/// #[derive(Clone, Copy)]
+/// struct Window {
+///     start: u64,
+///     end: u64,
+/// }
+///
+/// #[pin_data]
+/// struct Clock {
+///     #[pin]
+///     inner: Mutex<u64>,
+///     #[pin]
+///     window: SeqCount<Window, u64>,
+/// }
+///
+/// let clock = Box::pin_init(pin_init!(&this in Clock {
+///     inner <- new_mutex!(0),
+///     // SAFETY: `inner` is initialised before `window`, and is never moved since `Clock` is
+///     // pinned.
+///     window <- new_seqcount!(unsafe { &(*this.as_ptr()).inner }, Window { start: 0, end: 0 }),
+/// }), GFP_KERNEL)?;
+///
+/// {
+///     let mut guard = clock.inner.lock();
+///     *guard += 1;
+///     let mut window = clock.window.write(&mut guard);
+///     window.start = 10;
+///     window.end = 20;
+/// }
+///
+/// assert_eq!(clock.window.read(|w| w.end - w.start), 10);
+/// # Ok::<(), Error>(())
+/// ```
+///
+/// [`seqcount_t`]: srctree/include/linux/seqlock.h
+#[pin_data]
+pub struct SeqCount<T: Copy, U> {
+    /// The kernel sequence counter.
+    #[pin]
+    count: Opaque<bindings::seqcount_t>,
+
+    /// Lockdep may track the address of the counter, so we require it to be pinned.
+    #[pin]
+    _pin: PhantomPinned,
+
+    owner: *const U,
+    data: UnsafeCell<T>,
+}
+
+// SAFETY: `SeqCount` can be transferred across thread boundaries iff the data it protects can.
+unsafe impl<T: Copy + Send, U> Send for SeqCount<T, U> {}
+
+// SAFETY: Writers are serialised by the external lock and readers only get validated copies, so
+// sharing `SeqCount` only requires sending the data between threads.
+unsafe impl<T: Copy + Send, U> Sync for SeqCount<T, U> {}
+
+impl<T: Copy, U> SeqCount<T, U> {
+    /// Constructs a new [`SeqCount`] initialiser, whose writers are serialised by `owner`.
+    ///
+    /// Like [`LockedBy`](super::LockedBy), it stores a raw pointer to the owner that is never
+    /// dereferenced, and is only used to ensure that the right lock is held by writers.
+    pub fn new<B: Backend>(
+        owner: &Lock<U, B>,
+        t: T,
+        name: &'static CStr,
+        key: &'static LockClassKey,
+    ) -> impl PinInit<Self> {
+        build_assert!(
+            size_of::<Lock<U, B>>() > 0,
+            "The lock type cannot be a ZST because it may be impossible to distinguish instances"
+        );
+        let owner: *const U = owner.data.get();
+        pin_init!(Self {
+            owner,
+            data: UnsafeCell::new(t),
+            _pin: PhantomPinned,
+            // SAFETY: `slot` is valid while the closure is called and both `name` and `key` have
+            // static lifetimes so they live indefinitely.
+            count <- Opaque::ffi_init(|slot| unsafe {
+                bindings::__seqcount_init(slot, name.as_char_ptr(), key.as_ptr())
+            }),
+        })
+    }
+
+    /// Starts a write section when the caller provides evidence (via a mutable owner) that the
+    /// owner is locked.
+    ///
+    /// Concurrent readers retry until the returned guard is dropped. Preemption is disabled while
+    /// the guard is alive, so the caller must not sleep until then.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `owner` is different from the data protected by the lock used in
+    /// [`new`](SeqCount::new).
+    pub fn write<'a>(&'a self, owner: &'a mut U) -> SeqCountWriteGuard<'a, T, U> {
+        build_assert!(
+            size_of::<U>() > 0,
+            "`U` cannot be a ZST because `owner` wouldn't be unique"
+        );
+        if !ptr::eq(owner, self.owner) {
+            panic!("mismatched owners");
+        }
+
+        // SAFETY: The counter was initialised in the constructor, and `owner` is evidence that the
+        // caller is the only writer.
+        unsafe { bindings::write_seqcount_begin(self.count.get()) };
+        SeqCountWriteGuard {
+            seq: self,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Calls `f` with a consistent snapshot of the protected data and returns its result.
+    ///
+    /// The snapshot is taken again if a writer modified the data in the meantime, so `f` is only
+    /// called once, with consistent data.
+    pub fn read<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        loop {
+            // SAFETY: The counter was initialised in the constructor.
+            let seq = unsafe { bindings::read_seqcount_begin(self.count.get()) };
+
+            // SAFETY: `data` is valid for reads. A writer may be modifying it concurrently, so the
+            // copy is only treated as initialised once the sequence check below succeeds.
+            let copy = unsafe { ptr::read_volatile(self.data.get().cast::<MaybeUninit<T>>()) };
+
+            // SAFETY: The counter was initialised in the constructor.
+            if unsafe { bindings::read_seqcount_retry(self.count.get(), seq) } == 0 {
+                // SAFETY: No writer was active while the copy was taken, so it is consistent.
+                return f(unsafe { copy.assume_init_ref() });
+            }
+        }
+    }
+
+    /// Returns a consistent snapshot of the protected data.
+    pub fn get(&self) -> T {
+        self.read(|data| *data)
+    }
+}
+
+/// A write section of a [`SeqCount`].
+///
+/// It gives exclusive access to the data, and ends the write section when it goes out of scope.
+#[must_use = "the write section ends immediately when the guard is unused"]
+pub struct SeqCountWriteGuard<'a, T: Copy, U> {
+    seq: &'a SeqCount<T, U>,
+    _not_send: PhantomData<(&'a mut U, *mut ())>,
+}
+
+impl<T: Copy, U> core::ops::Deref for SeqCountWriteGuard<'_, T, U> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: The guard is the only writer, so it is safe to deref the protected data.
+        unsafe { &*self.seq.data.get() }
+    }
+}
+
+impl<T: Copy, U> core::ops::DerefMut for SeqCountWriteGuard<'_, T, U> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: The guard is the only writer, so it is safe to deref the protected data.
+        unsafe { &mut *self.seq.data.get() }
+    }
+}
+
+impl<T: Copy, U> Drop for SeqCountWriteGuard<'_, T, U> {
+    fn drop(&mut self) {
+        // SAFETY: The write section was started when the guard was created.
+        unsafe { bindings::write_seqcount_end(self.seq.count.get()) };
+    }
+}
@@ -0,0 +1,260 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! A wait queue.
+//!
+//! This module allows Rust code to use the kernel's [`struct wait_queue_head`] to wait for a
+//! condition to become true.
+//!
+//! [`struct wait_queue_head`]: srctree/include/linux/wait.h
+
+use super::{rcu, LockClassKey};
+use crate::{
+    error::{code::*, Result},
+    init::PinInit,
+    pin_init,
+    str::CStr,
+    task::{MAX_SCHEDULE_TIMEOUT, TASK_INTERRUPTIBLE, TASK_NORMAL, TASK_UNINTERRUPTIBLE},
+    time::{delta_to_timeout, jiffies_to_delta, Delta, Jiffies},
+    types::Opaque,
+};
+use core::{
+    ffi::{c_int, c_long, c_ulong},
+    marker::PhantomPinned,
+    pin::Pin,
+    ptr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use macros::{pin_data, pinned_drop};
+
+/// Creates a [`WaitQueue`] initialiser with the given name and a newly-created lock class.
+#[macro_export]
+macro_rules! new_wait_queue {
+    ($($name:literal)?) => {
+        $crate::sync::WaitQueue::new(
+            $crate::optional_name!($($name)?), $crate::static_lock_class!())
+    };
+}
+pub use new_wait_queue;
+
+/// A wait queue.
+///
+/// Exposes the kernel's [`struct wait_queue_head`]. It allows threads to sleep until a condition,
+/// given as a closure, becomes true. Threads that change the outcome of the condition call
+/// [`WaitQueue::wake_up`] or [`WaitQueue::wake_up_all`] afterwards, so that waiters check it again.
+///
+/// Unlike with a [`CondVar`], the condition is not protected by a lock: it is usually evaluated on
+/// atomics or other lockless state, and it may be evaluated any number of times.
+///
+/// A wait queue can also be registered in a [`PollTable`] from a file `poll` implementation, so
+/// that the pollers are woken up by the same calls.
+///
+/// Instances of [`WaitQueue`] need a lock class and to be pinned. The recommended way to create
+/// such instances is with the [`pin_init`](crate::pin_init) and [`new_wait_queue`] macros.
+///
+/// # Examples
+///
+/// The following example shows a counter whose consumers wait until it becomes non-zero:
+///
+/// ```
+/// use core::sync::atomic::{AtomicU32, Ordering};
+/// use kernel::sync::{new_wait_queue, WaitQueue};
+/// use kernel::time::Delta;
+///
+/// #[pin_data]
+/// struct Counter {
+///     value: AtomicU32,
+///     #[pin]
+///     wait: WaitQueue,
+/// }
+///
+/// impl Counter {
+///     fn add(&self, n: u32) {
+///         self.value.fetch_add(n, Ordering::Release);
+///         self.wait.wake_up_all();
+///     }
+///
+///     fn take(&self) -> u32 {
+///         self.wait.wait_event(|| self.value.load(Ordering::Acquire) != 0);
+///         self.value.swap(0, Ordering::Acquire)
+///     }
+/// }
+///
+/// let counter = Box::pin_init(pin_init!(Counter {
+///     value: AtomicU32::new(0),
+///     wait <- new_wait_queue!(),
+/// }), GFP_KERNEL)?;
+///
+/// let ready = || counter.value.load(Ordering::Acquire) != 0;
+/// assert!(counter.wait.wait_event_timeout(ready, Delta::from_millis(10)).is_none());
+///
+/// counter.add(3);
+/// assert!(counter.wait.wait_event_timeout(ready, Delta::from_millis(10)).is_some());
+/// assert_eq!(counter.take(), 3);
+/// # Ok::<(), Error>(())
+/// ```
+///
+/// [`CondVar`]: super::CondVar
+/// [`PollTable`]: super::poll::PollTable
+/// [`struct wait_queue_head`]: srctree/include/linux/wait.h
+#[pin_data(PinnedDrop)]
+pub struct WaitQueue {
+    #[pin]
+    pub(crate) wait_queue_head: Opaque<bindings::wait_queue_head>,
+
+    /// Whether the wait queue was ever registered in a poll table.
+    pub(crate) polled: AtomicBool,
+
+    /// A wait queue needs to be pinned because it contains a [`struct list_head`] that is
+    /// self-referential, so it cannot be safely moved once it is initialised.
+    ///
+    /// [`struct list_head`]: srctree/include/linux/types.h
+    #[pin]
+    _pin: PhantomPinned,
+}
+
+// SAFETY: `WaitQueue` only uses a `struct wait_queue_head`, which is safe to use on any thread.
+#[allow(clippy::non_send_fields_in_send_ty)]
+unsafe impl Send for WaitQueue {}
+
+// SAFETY: `WaitQueue` only uses a `struct wait_queue_head`, which is safe to use on multiple
+// threads concurrently.
+unsafe impl Sync for WaitQueue {}
+
+impl WaitQueue {
+    /// Constructs a new wait queue initialiser.
+    pub fn new(name: &'static CStr, key: &'static LockClassKey) -> impl PinInit<Self> {
+        pin_init!(Self {
+            polled: AtomicBool::new(false),
+            _pin: PhantomPinned,
+            // SAFETY: `slot` is valid while the closure is called and both `name` and `key` have
+            // static lifetimes so they live indefinitely.
+            wait_queue_head <- Opaque::ffi_init(|slot| unsafe {
+                bindings::__init_waitqueue_head(slot, name.as_char_ptr(), key.as_ptr())
+            }),
+        })
+    }
+
+    /// Sleeps until `cond` returns `true`, for at most `timeout` jiffies.
+    ///
+    /// Returns the remaining jiffies (at least 1) if the condition became true, 0 on timeout, or
+    /// [`ERESTARTSYS`] if the wait is interruptible and a signal arrived.
+    fn wait_internal(
+        &self,
+        wait_state: c_int,
+        mut cond: impl FnMut() -> bool,
+        timeout: c_long,
+    ) -> Result<c_long> {
+        if cond() {
+            return Ok(timeout.max(1));
+        }
+
+        let wait = Opaque::<bindings::wait_queue_entry>::uninit();
+
+        // SAFETY: `wait` points to valid memory.
+        unsafe { bindings::init_wait_entry(wait.get(), 0) };
+
+        let mut remaining = timeout;
+        let ret = loop {
+            // The task state is set before the condition is evaluated, so a wake up that happens
+            // after the evaluation is not missed.
+            //
+            // SAFETY: Both `wait` and `wait_queue_head` point to valid memory.
+            let interrupted = unsafe {
+                bindings::prepare_to_wait_event(self.wait_queue_head.get(), wait.get(), wait_state)
+            };
+
+            if cond() {
+                break Ok(remaining.max(1));
+            }
+            if interrupted != 0 {
+                break Err(ERESTARTSYS);
+            }
+            if remaining == 0 {
+                break Ok(0);
+            }
+
+            // SAFETY: Switches to another thread. The timeout can be any number.
+            remaining = unsafe { bindings::schedule_timeout(remaining) };
+        };
+
+        // SAFETY: Both `wait` and `wait_queue_head` point to valid memory.
+        unsafe { bindings::finish_wait(self.wait_queue_head.get(), wait.get()) };
+
+        ret
+    }
+
+    /// Sleeps in uninterruptible mode until `cond` returns `true`.
+    ///
+    /// `cond` is evaluated every time the wait queue is woken up, so it may be called any number
+    /// of times.
+    pub fn wait_event(&self, cond: impl FnMut() -> bool) {
+        // Uninterruptible waits without a timeout can only return once the condition is true.
+        let _ = self.wait_internal(TASK_UNINTERRUPTIBLE, cond, MAX_SCHEDULE_TIMEOUT);
+    }
+
+    /// Sleeps in interruptible mode until `cond` returns `true`.
+    ///
+    /// Similar to [`WaitQueue::wait_event`], except that the thread may also wake up due to
+    /// signals, in which case it returns [`ERESTARTSYS`].
+    pub fn wait_event_interruptible(&self, cond: impl FnMut() -> bool) -> Result {
+        self.wait_internal(TASK_INTERRUPTIBLE, cond, MAX_SCHEDULE_TIMEOUT)
+            .map(|_| ())
+    }
+
+    /// Sleeps in uninterruptible mode until `cond` returns `true`, for at most `timeout`.
+    ///
+    /// Returns the remaining time if the condition became true, or [`None`] on timeout. The
+    /// timeout has jiffy granularity.
+    #[must_use = "wait_event_timeout returns None on timeout, so the caller must check it"]
+    pub fn wait_event_timeout(&self, cond: impl FnMut() -> bool, timeout: Delta) -> Option<Delta> {
+        let jiffies = delta_to_timeout(timeout).as_raw() as c_long;
+        match self.wait_internal(TASK_UNINTERRUPTIBLE, cond, jiffies) {
+            Ok(0) | Err(_) => None,
+            Ok(left) => Some(jiffies_to_delta(Jiffies::from_raw(left as c_ulong))),
+        }
+    }
+
+    /// Calls the kernel function to wake up the appropriate number of exclusive waiters.
+    fn wake(&self, count: c_int) {
+        // SAFETY: `wait_queue_head` points to valid memory.
+        unsafe {
+            bindings::__wake_up(
+                self.wait_queue_head.get(),
+                TASK_NORMAL,
+                count,
+                ptr::null_mut(),
+            )
+        };
+    }
+
+    /// Wakes up the waiters, so that they evaluate their conditions again.
+    ///
+    /// All waiters of the `wait_event` functions are woken up, since they wait non-exclusively;
+    /// only a single one of any exclusive waiters is.
+    pub fn wake_up(&self) {
+        self.wake(1);
+    }
+
+    /// Wakes up all waiters, including exclusive ones.
+    pub fn wake_up_all(&self) {
+        self.wake(0);
+    }
+}
+
+#[pinned_drop]
+impl PinnedDrop for WaitQueue {
+    fn drop(self: Pin<&mut Self>) {
+        if !self.polled.load(Ordering::Relaxed) {
+            return;
+        }
+
+        // Pollers may still have entries in the wait queue, so they must be removed before it goes
+        // away.
+        //
+        // SAFETY: `wait_queue_head` was initialised in the constructor.
+        unsafe { bindings::wake_up_pollfree(self.wait_queue_head.get()) };
+
+        // `epoll` removes its entries under RCU, so wait for a grace period before freeing.
+        rcu::synchronize_rcu();
+    }
+}
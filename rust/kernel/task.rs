@@ -4,14 +4,28 @@
 //!
 //! C header: [`include/linux/sched.h`](srctree/include/linux/sched.h).
 
-use crate::types::Opaque;
+use crate::{
+    cred::Credential,
+    error::{to_result, Result},
+    pid::{Pid, PidNamespace},
+    str::BStr,
+    sync::rcu,
+    time::{delta_to_timeout, jiffies_to_delta, Delta, Jiffies},
+    types::{ARef, Opaque},
+};
 use core::{
-    ffi::{c_int, c_long, c_uint},
+    ffi::{c_int, c_long, c_uint, c_ulong},
     marker::PhantomData,
     ops::Deref,
     ptr,
 };
 
+mod kthread;
+mod signal;
+
+pub use kthread::{JoinHandle, KThread, ShouldStop};
+pub use signal::Signal;
+
 /// A sentinel value used for infinite timeouts.
 pub const MAX_SCHEDULE_TIMEOUT: c_long = c_long::MAX;
 
@@ -19,6 +33,8 @@ pub const MAX_SCHEDULE_TIMEOUT: c_long = c_long::MAX;
 pub const TASK_INTERRUPTIBLE: c_int = bindings::TASK_INTERRUPTIBLE as c_int;
 /// Bitmask for tasks that are sleeping in an uninterruptible state.
 pub const TASK_UNINTERRUPTIBLE: c_int = bindings::TASK_UNINTERRUPTIBLE as c_int;
+/// Bitmask for tasks that are sleeping in an uninterruptible state, except for fatal signals.
+pub const TASK_KILLABLE: c_int = bindings::TASK_KILLABLE as c_int;
 /// Convenience constant for waking up tasks regardless of whether they are in interruptible or
 /// uninterruptible sleep.
 pub const TASK_NORMAL: c_uint = bindings::TASK_NORMAL as c_uint;
@@ -91,8 +107,31 @@ unsafe impl Send for Task {}
 // synchronised by C code (e.g., `signal_pending`).
 unsafe impl Sync for Task {}
 
-/// The type of process identifiers (PIDs).
-type Pid = bindings::pid_t;
+/// The type of process identifiers (PIDs) as numbers.
+///
+/// See [`Pid`] for a reference to a PID that cannot be reused by another task.
+pub type RawPid = bindings::pid_t;
+
+/// The size of the buffer that holds the name of a task, including the `NUL` terminator.
+pub const TASK_COMM_LEN: usize = bindings::TASK_COMM_LEN as usize;
+
+/// A copy of the name of a task, as returned by [`Task::comm`].
+pub struct Comm {
+    buf: [u8; TASK_COMM_LEN],
+}
+
+impl Deref for Comm {
+    type Target = BStr;
+
+    fn deref(&self) -> &BStr {
+        let len = self
+            .buf
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(TASK_COMM_LEN);
+        BStr::from_bytes(&self.buf[..len])
+    }
+}
 
 impl Task {
     /// Returns a task reference for the currently executing task/thread.
@@ -142,18 +181,143 @@ impl Task {
     }
 
     /// Returns the PID of the given task.
-    pub fn pid(&self) -> Pid {
+    pub fn pid(&self) -> RawPid {
         // SAFETY: By the type invariant, we know that `self.0` is a valid task. Valid tasks always
         // have a valid pid.
         unsafe { *ptr::addr_of!((*self.0.get()).pid) }
     }
 
+    /// Returns the thread group ID of the given task, that is, the PID of its process.
+    pub fn tgid(&self) -> RawPid {
+        // SAFETY: By the type invariant, we know that `self.0` is a valid task. Valid tasks always
+        // have a valid tgid.
+        unsafe { *ptr::addr_of!((*self.0.get()).tgid) }
+    }
+
+    /// Returns a copy of the name of the given task (`__get_task_comm`).
+    ///
+    /// The name may be changed concurrently by the task itself, which is why it is copied.
+    pub fn comm(&self) -> Comm {
+        let mut comm = Comm {
+            buf: [0; TASK_COMM_LEN],
+        };
+        // SAFETY: By the type invariant, we know that `self.0` is valid. The buffer is large
+        // enough for any name, which is `NUL`-terminated and padded by `__get_task_comm`.
+        unsafe {
+            bindings::__get_task_comm(comm.buf.as_mut_ptr().cast(), TASK_COMM_LEN, self.0.get())
+        };
+        comm
+    }
+
+    /// Returns the PID of the given task in the PID namespace of the current task
+    /// (`task_pid_vnr`).
+    ///
+    /// Unlike [`Task::pid`], which is the PID in the initial namespace, this is the number that
+    /// userspace knows the task by. Returns 0 if the task is not visible from that namespace, or
+    /// if it has exited.
+    pub fn pid_in_current_ns(&self) -> RawPid {
+        // SAFETY: By the type invariant, we know that `self.0` is valid. A null namespace stands
+        // for the one of the current task.
+        unsafe {
+            bindings::__task_pid_nr_ns(
+                self.0.get(),
+                bindings::pid_type_PIDTYPE_PID,
+                ptr::null_mut(),
+            )
+        }
+    }
+
+    /// Returns the PID namespace of the given task (`task_active_pid_ns`).
+    ///
+    /// Returns [`None`] if the task has exited.
+    pub fn get_pid_ns(&self) -> Option<ARef<PidNamespace>> {
+        let _guard = rcu::read_lock();
+
+        // SAFETY: By the type invariant, we know that `self.0` is valid. The RCU read side lock is
+        // held, as required by `task_active_pid_ns`.
+        let ptr = unsafe { bindings::task_active_pid_ns(self.0.get()) };
+        if ptr.is_null() {
+            return None;
+        }
+
+        // SAFETY: The namespace is valid while the RCU read side lock is held, and a new reference
+        // is taken before it is released.
+        Some(ARef::from(unsafe { PidNamespace::from_ptr(ptr) }))
+    }
+
+    /// Returns a reference to the PID of the given task (`get_task_pid`).
+    ///
+    /// Use it on the [`Task::group_leader`] to refer to the process. Returns [`None`] if the task
+    /// has exited.
+    pub fn get_pid(&self) -> Option<ARef<Pid>> {
+        // SAFETY: By the type invariant, we know that `self.0` is valid. `get_task_pid` returns
+        // either null or a new reference to a valid pid.
+        let ptr = unsafe { bindings::get_task_pid(self.0.get(), bindings::pid_type_PIDTYPE_PID) };
+
+        // SAFETY: If `ptr` is non-null, it is valid and we own the reference that was taken above.
+        ptr::NonNull::new(ptr).map(|ptr| unsafe { ARef::from_raw(ptr.cast()) })
+    }
+
+    /// Looks up the task with the PID `nr` in the PID namespace of the current task
+    /// (`find_task_by_vpid`).
+    ///
+    /// Returns [`None`] if there is no such task.
+    pub fn find_by_vpid(nr: RawPid) -> Option<ARef<Task>> {
+        let _guard = rcu::read_lock();
+
+        // SAFETY: The RCU read side lock is held, as required by `find_task_by_vpid`.
+        let ptr = unsafe { bindings::find_task_by_vpid(nr) };
+        if ptr.is_null() {
+            return None;
+        }
+
+        // SAFETY: The task is valid while the RCU read side lock is held, and a new reference is
+        // taken before it is released.
+        Some(ARef::from(unsafe { &*ptr.cast::<Task>() }))
+    }
+
+    /// Returns the objective credentials of the given task (`get_task_cred`).
+    ///
+    /// These are the credentials used when the task is acted upon, e.g., when it is sent a signal.
+    /// Permission checks of the current task must use its subjective credentials instead, which
+    /// are returned by [`Credential::current`].
+    pub fn cred(&self) -> ARef<Credential> {
+        // SAFETY: By the type invariant, we know that `self.0` is valid. `get_task_cred` returns
+        // a new reference to valid credentials.
+        let ptr = unsafe { bindings::get_task_cred(self.0.get()) };
+
+        // SAFETY: `ptr` is valid and non-null, and we own the reference that was taken above.
+        unsafe { ARef::from_raw(ptr::NonNull::new_unchecked(ptr.cast_mut().cast())) }
+    }
+
     /// Determines whether the given task has pending signals.
     pub fn signal_pending(&self) -> bool {
         // SAFETY: By the type invariant, we know that `self.0` is valid.
         unsafe { bindings::signal_pending(self.0.get()) != 0 }
     }
 
+    /// Determines whether the given task has a fatal signal pending, that is, whether it is being
+    /// killed (`fatal_signal_pending`).
+    ///
+    /// Killable sleeps should return an error when it does, usually [`EINTR`].
+    ///
+    /// [`EINTR`]: crate::error::code::EINTR
+    pub fn fatal_signal_pending(&self) -> bool {
+        // SAFETY: By the type invariant, we know that `self.0` is valid.
+        unsafe { bindings::fatal_signal_pending(self.0.get()) != 0 }
+    }
+
+    /// Sends the signal `sig` to the given task, on behalf of the kernel (`send_sig`).
+    ///
+    /// Returns [`ESRCH`] if the task has exited.
+    ///
+    /// [`ESRCH`]: crate::error::code::ESRCH
+    pub fn send_signal(&self, sig: Signal) -> Result {
+        // SAFETY: By the type invariant, we know that `self.0` is valid. Sending a signal is
+        // properly synchronised by C code, even if the task is exiting.
+        to_result(unsafe { bindings::send_sig(sig.as_raw(), self.0.get(), 1) })
+    }
+
     /// Wakes up the task.
     pub fn wake_up(&self) {
         // SAFETY: By the type invariant, we know that `self.0.get()` is non-null and valid.
@@ -163,6 +327,217 @@ impl Task {
     }
 }
 
+/// The state of a task that is about to sleep, as set by [`set_current_state`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TaskState {
+    /// The task is woken up by signals ([`TASK_INTERRUPTIBLE`]).
+    Interruptible,
+    /// The task is not woken up by signals ([`TASK_UNINTERRUPTIBLE`]).
+    Uninterruptible,
+    /// The task is only woken up by fatal signals ([`TASK_KILLABLE`]).
+    Killable,
+}
+
+impl TaskState {
+    fn as_raw(self) -> c_int {
+        match self {
+            Self::Interruptible => TASK_INTERRUPTIBLE,
+            Self::Uninterruptible => TASK_UNINTERRUPTIBLE,
+            Self::Killable => TASK_KILLABLE,
+        }
+    }
+}
+
+/// Sets the state of the current task, before it checks its sleep condition and goes to sleep
+/// (`set_current_state`).
+///
+/// The state is reset to running when the returned guard is dropped, so that it cannot be
+/// forgotten when the condition turns out to be already true. The guard is consumed by going to
+/// sleep, after which the task is running again.
+///
+/// # Examples
+///
+/// The following example shows a killable wait for a flag that is set by another thread, which
+/// then wakes up the waiting task:
+///
+/// ```
+/// use core::sync::atomic::{AtomicBool, Ordering};
+/// use kernel::task::{set_current_state, TaskState};
+///
+/// fn wait_for(done: &AtomicBool) -> Result {
+///     loop {
+///         let state = set_current_state(TaskState::Killable);
+///         if done.load(Ordering::Acquire) {
+///             return Ok(());
+///         }
+///         if current!().fatal_signal_pending() {
+///             return Err(EINTR);
+///         }
+///         state.schedule();
+///     }
+/// }
+///
+/// let done = AtomicBool::new(true);
+/// wait_for(&done)?;
+/// # Ok::<(), Error>(())
+/// ```
+#[inline(always)]
+pub fn set_current_state(state: TaskState) -> CurrentStateGuard {
+    // SAFETY: FFI call without safety requirements. The state is reset by the guard. With
+    // `CONFIG_DEBUG_ATOMIC_SLEEP`, the helper records its return address as the place where the
+    // state changed, which is in the caller since this function is always inlined.
+    unsafe { bindings::set_current_state(state.as_raw() as c_uint) };
+    // INVARIANT: The state of the current task was just set above.
+    CurrentStateGuard {
+        _not_send: PhantomData,
+    }
+}
+
+/// A guard that resets the state of the current task to running when dropped.
+///
+/// It is returned by [`set_current_state`]. It is not [`Send`] because the state is the one of the
+/// current task.
+///
+/// # Invariants
+///
+/// The state of the current task was set by [`set_current_state`].
+#[must_use = "the task state is reset when the guard is dropped"]
+pub struct CurrentStateGuard {
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl CurrentStateGuard {
+    /// Puts the current task to sleep until it is woken up (`schedule`).
+    ///
+    /// The task is only woken up by the signals that its state allows, or by [`Task::wake_up`].
+    pub fn schedule(self) {
+        // SAFETY: FFI call without safety requirements.
+        unsafe { bindings::schedule() };
+    }
+
+    /// Puts the current task to sleep until it is woken up, or until `timeout` elapses
+    /// (`schedule_timeout`).
+    ///
+    /// Returns the remaining time, which is zero if the timeout elapsed. A timeout of
+    /// [`Delta::MAX`] means waiting forever, in which case [`Delta::MAX`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// A task that is woken up after setting its state does not sleep, and gets back the whole
+    /// timeout:
+    ///
+    /// ```
+    /// use kernel::task::{set_current_state, TaskState};
+    /// use kernel::time::Delta;
+    ///
+    /// let state = set_current_state(TaskState::Interruptible);
+    /// current!().wake_up();
+    /// assert_eq!(state.schedule_timeout(Delta::MAX), Delta::MAX);
+    ///
+    /// let timeout = Delta::from_secs(10);
+    /// let state = set_current_state(TaskState::Interruptible);
+    /// current!().wake_up();
+    /// let remaining = state.schedule_timeout(timeout);
+    /// assert!(remaining > Delta::ZERO && remaining <= timeout);
+    /// ```
+    pub fn schedule_timeout(self, timeout: Delta) -> Delta {
+        // SAFETY: FFI call without safety requirements. The timeout is at most
+        // `MAX_SCHEDULE_TIMEOUT`.
+        let remaining =
+            unsafe { bindings::schedule_timeout(delta_to_timeout(timeout).as_raw() as c_long) };
+        if remaining == MAX_SCHEDULE_TIMEOUT {
+            return Delta::MAX;
+        }
+        jiffies_to_delta(Jiffies::from_raw(remaining as c_ulong))
+    }
+}
+
+impl Drop for CurrentStateGuard {
+    fn drop(&mut self) {
+        // SAFETY: FFI call without safety requirements. The task is running, so it is always
+        // correct to say so.
+        unsafe { bindings::__set_current_state(bindings::TASK_RUNNING) };
+    }
+}
+
+/// Puts the current task to sleep until `timeout` elapses, or until it receives a signal
+/// (`schedule_timeout_interruptible`).
+///
+/// Returns the remaining time, which is zero if the timeout elapsed. A timeout of [`Delta::MAX`]
+/// means waiting forever, in which case [`Delta::MAX`] is returned.
+pub fn schedule_timeout_interruptible(timeout: Delta) -> Delta {
+    set_current_state(TaskState::Interruptible).schedule_timeout(timeout)
+}
+
+/// Puts the current task to sleep until `timeout` elapses, or until it receives a fatal signal
+/// (`schedule_timeout_killable`).
+///
+/// Returns the remaining time, which is zero if the timeout elapsed. A timeout of [`Delta::MAX`]
+/// means waiting forever, in which case [`Delta::MAX`] is returned.
+///
+/// # Examples
+///
+/// ```
+/// use kernel::task::schedule_timeout_killable;
+/// use kernel::time::Delta;
+///
+/// let remaining = schedule_timeout_killable(Delta::from_millis(10));
+/// if current!().fatal_signal_pending() {
+///     return Err(EINTR);
+/// }
+/// assert!(remaining.is_zero());
+/// # Ok::<(), Error>(())
+/// ```
+pub fn schedule_timeout_killable(timeout: Delta) -> Delta {
+    set_current_state(TaskState::Killable).schedule_timeout(timeout)
+}
+
+/// Annotates a function that may sleep.
+///
+/// When `CONFIG_DEBUG_ATOMIC_SLEEP` is enabled, it warns if it is called from atomic context, e.g.,
+/// while holding a spinlock or with interrupts disabled. The warning reports the location of the
+/// caller. It may also be a preemption point.
+#[track_caller]
+#[inline]
+pub fn might_sleep() {
+    #[cfg(CONFIG_DEBUG_ATOMIC_SLEEP)]
+    {
+        let loc = core::panic::Location::caller();
+        let file = FileName::new(loc.file());
+        // SAFETY: `file` is a NUL-terminated string, and `__might_sleep` only prints it.
+        unsafe { bindings::__might_sleep(file.as_ptr(), loc.line() as c_int) }
+    }
+
+    // SAFETY: FFI call without safety requirements.
+    unsafe { bindings::might_resched() }
+}
+
+/// The longest file name that [`might_sleep`] reports, including the NUL terminator.
+#[cfg(CONFIG_DEBUG_ATOMIC_SLEEP)]
+const FILE_NAME_LEN: usize = 128;
+
+/// A NUL-terminated copy of a file name, as expected by C.
+///
+/// The file names of [`core::panic::Location`] are not NUL-terminated. Longer names are truncated
+/// from the start, so that the name of the file itself is kept.
+#[cfg(CONFIG_DEBUG_ATOMIC_SLEEP)]
+struct FileName([u8; FILE_NAME_LEN]);
+
+#[cfg(CONFIG_DEBUG_ATOMIC_SLEEP)]
+impl FileName {
+    fn new(name: &str) -> Self {
+        let name = name.as_bytes();
+        let name = &name[name.len().saturating_sub(FILE_NAME_LEN - 1)..];
+        let mut buf = [0; FILE_NAME_LEN];
+        buf[..name.len()].copy_from_slice(name);
+        Self(buf)
+    }
+
+    fn as_ptr(&self) -> *const core::ffi::c_char {
+        self.0.as_ptr().cast()
+    }
+}
+
 // SAFETY: The type invariants guarantee that `Task` is always refcounted.
 unsafe impl crate::types::AlwaysRefCounted for Task {
     fn inc_ref(&self) {
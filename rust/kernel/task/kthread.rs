@@ -0,0 +1,332 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Kernel threads.
+//!
+//! C header: [`include/linux/kthread.h`](srctree/include/linux/kthread.h)
+
+use super::Task;
+use crate::{
+    alloc::NumaNode,
+    c_str,
+    error::{from_err_ptr, to_result},
+    new_completion,
+    percpu::cpu_possible,
+    prelude::*,
+    sync::Completion,
+    types::ARef,
+};
+use core::{
+    cell::UnsafeCell,
+    ffi::{c_int, c_void},
+    marker::PhantomData,
+    mem::ManuallyDrop,
+    ptr,
+};
+
+/// A builder for kernel threads.
+///
+/// The thread runs a closure, which receives a [`ShouldStop`] token and returns a value of any
+/// [`Send`] type. It is started by [`KThread::spawn`], which returns a [`JoinHandle`] that stops
+/// the thread and gives back the value.
+///
+/// # Examples
+///
+/// The following example shows a thread that handles events until it is stopped:
+///
+/// ```
+/// use kernel::c_str;
+/// use kernel::sync::{new_wait_queue, Arc, Atomic, WaitQueue};
+/// use kernel::task::KThread;
+///
+/// #[pin_data]
+/// struct Events {
+///     pending: Atomic<i32>,
+///     #[pin]
+///     wait: WaitQueue,
+/// }
+///
+/// let events = Arc::pin_init(pin_init!(Events {
+///     pending: Atomic::new(0),
+///     wait <- new_wait_queue!(),
+/// }), GFP_KERNEL)?;
+///
+/// let thread = KThread::new(c_str!("rust_poller")).cpu(0).spawn({
+///     let events = events.clone();
+///     move |stop| {
+///         let mut handled = 0;
+///         loop {
+///             let _ = events.wait.wait_event_interruptible(|| {
+///                 stop.should_stop() || stop.should_park() || events.pending.load() != 0
+///             });
+///             if stop.should_stop() {
+///                 break handled + events.pending.xchg(0);
+///             }
+///             stop.parkme();
+///             handled += events.pending.xchg(0);
+///         }
+///     }
+/// })?;
+///
+/// events.pending.add(2);
+/// events.wait.wake_up();
+///
+/// // While the thread is parked, events are left pending.
+/// thread.park()?;
+/// events.pending.add(1);
+/// thread.unpark();
+///
+/// assert_eq!(thread.stop(), 3);
+/// # Ok::<(), Error>(())
+/// ```
+pub struct KThread<'a> {
+    name: &'a CStr,
+    cpu: Option<u32>,
+    node: NumaNode,
+}
+
+impl<'a> KThread<'a> {
+    /// Creates a new builder for a thread with the given name.
+    ///
+    /// The name is copied, and truncated to the maximum length of task names.
+    pub fn new(name: &'a CStr) -> Self {
+        Self {
+            name,
+            cpu: None,
+            node: NumaNode::NO_NODE,
+        }
+    }
+
+    /// Binds the thread to the given CPU (`kthread_bind`).
+    pub fn cpu(self, cpu: u32) -> Self {
+        Self {
+            cpu: Some(cpu),
+            ..self
+        }
+    }
+
+    /// Allocates the thread's stack and task structure on the given NUMA node.
+    pub fn node(self, node: NumaNode) -> Self {
+        Self { node, ..self }
+    }
+
+    /// Creates the thread and starts running `f` on it (`kthread_run`).
+    ///
+    /// It waits until the thread starts running `f`, so it may sleep. Returns [`EINVAL`] if the
+    /// CPU given to [`KThread::cpu`] is not a possible CPU.
+    pub fn spawn<F, T>(self, f: F) -> Result<JoinHandle<T>>
+    where
+        F: FnOnce(&ShouldStop) -> T + Send + 'static,
+        T: Send,
+    {
+        if self.cpu.is_some_and(|cpu| !cpu_possible(cpu)) {
+            return Err(EINVAL);
+        }
+
+        let data = Box::pin_init(
+            pin_init!(ThreadData {
+                started <- new_completion!("KThread::started"),
+                func: UnsafeCell::new(Some(f)),
+                result: UnsafeCell::new(None),
+            }),
+            GFP_KERNEL,
+        )?;
+        // SAFETY: The allocation is only freed by `ThreadData::finish`, and never moved.
+        let data = Box::into_raw(unsafe { Pin::into_inner_unchecked(data) });
+
+        // SAFETY: `thread_fn` can be called with `data` as argument until it is freed, which only
+        // happens once the thread exits. The name is copied, and passed as an argument of the
+        // format string so that it is not interpreted.
+        let task = from_err_ptr(unsafe {
+            bindings::kthread_create_on_node(
+                Some(ThreadData::<F, T>::thread_fn),
+                data.cast(),
+                self.node.as_raw(),
+                c_str!("%s").as_char_ptr(),
+                self.name.as_char_ptr(),
+            )
+        });
+        let task = match task {
+            Ok(task) => task,
+            Err(e) => {
+                // SAFETY: The thread was not created, so `data` is not used by anyone else.
+                drop(unsafe { ThreadData::<F, T>::finish(data.cast()) });
+                return Err(e);
+            }
+        };
+
+        // SAFETY: `kthread_create_on_node` returned a valid task. The new reference keeps it valid
+        // until the handle is dropped, even after the thread exits.
+        let task = ARef::from(unsafe { &*task.cast::<Task>() });
+
+        if let Some(cpu) = self.cpu {
+            // SAFETY: The thread was just created and has not been woken up yet, and `cpu` is a
+            // possible CPU.
+            unsafe { bindings::kthread_bind(task.0.get(), cpu) };
+        }
+
+        task.wake_up();
+
+        // SAFETY: `data` is valid until the thread exits, which cannot happen before `started` is
+        // completed, since the thread cannot be stopped before the handle exists.
+        unsafe { (*data).started.wait() };
+
+        // INVARIANT: The thread was created with `data`, and is now running `f`.
+        Ok(JoinHandle {
+            task,
+            data: data.cast(),
+            finish: ThreadData::<F, T>::finish,
+        })
+    }
+}
+
+/// Evidence that the current thread is a kernel thread created by [`KThread::spawn`].
+///
+/// It is given to the closure that runs on the thread, to check whether it should stop or park.
+pub struct ShouldStop {
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl ShouldStop {
+    /// Returns whether [`JoinHandle::stop`] was called (`kthread_should_stop`).
+    ///
+    /// The thread should return from its closure when it does.
+    pub fn should_stop(&self) -> bool {
+        // SAFETY: The existence of `self` proves that the current thread is a kernel thread.
+        unsafe { bindings::kthread_should_stop() }
+    }
+
+    /// Returns whether [`JoinHandle::park`] was called (`kthread_should_park`).
+    pub fn should_park(&self) -> bool {
+        // SAFETY: The existence of `self` proves that the current thread is a kernel thread.
+        unsafe { bindings::kthread_should_park() }
+    }
+
+    /// Parks the thread if [`JoinHandle::park`] was called, until [`JoinHandle::unpark`] is
+    /// (`kthread_parkme`).
+    ///
+    /// It returns immediately otherwise.
+    pub fn parkme(&self) {
+        // SAFETY: The existence of `self` proves that the current thread is a kernel thread.
+        unsafe { bindings::kthread_parkme() }
+    }
+}
+
+/// A handle to a kernel thread created by [`KThread::spawn`].
+///
+/// The thread is stopped when the handle is dropped, and the value it returned is dropped.
+///
+/// # Invariants
+///
+/// `task` is a kernel thread that was created with `data` as argument, and `finish` is the
+/// function that frees `data`, once the thread has exited.
+pub struct JoinHandle<T> {
+    task: ARef<Task>,
+    data: *mut c_void,
+    finish: unsafe fn(*mut c_void) -> Option<T>,
+}
+
+// SAFETY: The handle only gives access to the task, which can be used from any thread, and to the
+// result, which is `Send`.
+unsafe impl<T: Send> Send for JoinHandle<T> {}
+
+// SAFETY: Shared references to the handle only give access to the task.
+unsafe impl<T> Sync for JoinHandle<T> {}
+
+impl<T> JoinHandle<T> {
+    /// Returns the task of the thread.
+    pub fn task(&self) -> &Task {
+        &self.task
+    }
+
+    /// Parks the thread (`kthread_park`).
+    ///
+    /// It waits until the thread calls [`ShouldStop::parkme`], so it may sleep. Returns [`ENOSYS`]
+    /// if the thread has already exited.
+    pub fn park(&self) -> Result {
+        // SAFETY: By the type invariants, `task` is a kernel thread.
+        to_result(unsafe { bindings::kthread_park(self.task.0.get()) })
+    }
+
+    /// Unparks the thread (`kthread_unpark`).
+    pub fn unpark(&self) {
+        // SAFETY: By the type invariants, `task` is a kernel thread.
+        unsafe { bindings::kthread_unpark(self.task.0.get()) }
+    }
+
+    /// Stops the thread and returns the value returned by its closure (`kthread_stop`).
+    ///
+    /// It makes [`ShouldStop::should_stop`] return `true` and wakes the thread up, then waits
+    /// until the closure returns, so it may sleep. The thread is unparked if needed.
+    pub fn stop(self) -> T {
+        let mut this = ManuallyDrop::new(self);
+        let result = this.stop_and_finish();
+
+        // SAFETY: `this` is not used anymore, and its destructor does not run.
+        drop(unsafe { ptr::read(&this.task) });
+
+        // The closure always runs to completion before the thread exits, since the thread cannot
+        // be stopped before `spawn` returns.
+        result.expect("kernel thread exited without returning a value")
+    }
+
+    fn stop_and_finish(&mut self) -> Option<T> {
+        // SAFETY: By the type invariants, `task` is a kernel thread. The handle holds a reference
+        // to it, so it is still valid even if it has already exited.
+        unsafe { bindings::kthread_stop(self.task.0.get()) };
+
+        // SAFETY: By the type invariants, `finish` frees `data`, and the thread has exited.
+        unsafe { (self.finish)(self.data) }
+    }
+}
+
+impl<T> Drop for JoinHandle<T> {
+    fn drop(&mut self) {
+        drop(self.stop_and_finish());
+    }
+}
+
+/// The state shared between a [`JoinHandle`] and its thread.
+#[pin_data]
+struct ThreadData<F, T> {
+    #[pin]
+    started: Completion,
+    func: UnsafeCell<Option<F>>,
+    result: UnsafeCell<Option<T>>,
+}
+
+impl<F, T> ThreadData<F, T>
+where
+    F: FnOnce(&ShouldStop) -> T,
+{
+    unsafe extern "C" fn thread_fn(ptr: *mut c_void) -> c_int {
+        // SAFETY: `ptr` is the `ThreadData` allocated by `KThread::spawn`, which is only freed once
+        // the thread has exited.
+        let data = unsafe { &*ptr.cast::<Self>() };
+
+        // SAFETY: The thread has exclusive access to `func` and `result` until it exits.
+        let func = unsafe { (*data.func.get()).take() };
+        data.started.complete();
+
+        if let Some(func) = func {
+            let result = func(&ShouldStop {
+                _not_send: PhantomData,
+            });
+            // SAFETY: The thread has exclusive access to `result` until it exits.
+            unsafe { *data.result.get() = Some(result) };
+        }
+        0
+    }
+
+    /// Frees the [`ThreadData`] and returns the value returned by the closure, if any.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been allocated by [`KThread::spawn`], and must not be used by the thread
+    /// anymore.
+    unsafe fn finish(ptr: *mut c_void) -> Option<T> {
+        // SAFETY: The safety requirements ensure that `ptr` comes from `Box::into_raw` and that
+        // nothing else uses it anymore.
+        let data = unsafe { Box::from_raw(ptr.cast::<Self>()) };
+        data.result.into_inner()
+    }
+}
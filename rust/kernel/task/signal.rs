@@ -0,0 +1,84 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Signals.
+//!
+//! C header: [`include/linux/signal.h`](srctree/include/linux/signal.h)
+
+use core::ffi::c_int;
+
+/// A standard signal, as sent by [`Task::send_signal`].
+///
+/// The numbers of the signals depend on the architecture. See `signal(7)` for their meaning.
+///
+/// [`Task::send_signal`]: super::Task::send_signal
+#[repr(u32)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Signal {
+    /// `SIGHUP`.
+    Hup = bindings::SIGHUP,
+    /// `SIGINT`.
+    Int = bindings::SIGINT,
+    /// `SIGQUIT`.
+    Quit = bindings::SIGQUIT,
+    /// `SIGILL`.
+    Ill = bindings::SIGILL,
+    /// `SIGTRAP`.
+    Trap = bindings::SIGTRAP,
+    /// `SIGABRT`.
+    Abrt = bindings::SIGABRT,
+    /// `SIGBUS`.
+    Bus = bindings::SIGBUS,
+    /// `SIGFPE`.
+    Fpe = bindings::SIGFPE,
+    /// `SIGKILL`, which cannot be caught or ignored.
+    Kill = bindings::SIGKILL,
+    /// `SIGUSR1`.
+    Usr1 = bindings::SIGUSR1,
+    /// `SIGSEGV`.
+    Segv = bindings::SIGSEGV,
+    /// `SIGUSR2`.
+    Usr2 = bindings::SIGUSR2,
+    /// `SIGPIPE`.
+    Pipe = bindings::SIGPIPE,
+    /// `SIGALRM`.
+    Alrm = bindings::SIGALRM,
+    /// `SIGTERM`.
+    Term = bindings::SIGTERM,
+    /// `SIGCHLD`.
+    Chld = bindings::SIGCHLD,
+    /// `SIGCONT`.
+    Cont = bindings::SIGCONT,
+    /// `SIGSTOP`, which cannot be caught or ignored.
+    Stop = bindings::SIGSTOP,
+    /// `SIGTSTP`.
+    Tstp = bindings::SIGTSTP,
+    /// `SIGTTIN`.
+    Ttin = bindings::SIGTTIN,
+    /// `SIGTTOU`.
+    Ttou = bindings::SIGTTOU,
+    /// `SIGURG`.
+    Urg = bindings::SIGURG,
+    /// `SIGXCPU`.
+    Xcpu = bindings::SIGXCPU,
+    /// `SIGXFSZ`.
+    Xfsz = bindings::SIGXFSZ,
+    /// `SIGVTALRM`.
+    Vtalrm = bindings::SIGVTALRM,
+    /// `SIGPROF`.
+    Prof = bindings::SIGPROF,
+    /// `SIGWINCH`.
+    Winch = bindings::SIGWINCH,
+    /// `SIGIO`.
+    Io = bindings::SIGIO,
+    /// `SIGPWR`.
+    Pwr = bindings::SIGPWR,
+    /// `SIGSYS`.
+    Sys = bindings::SIGSYS,
+}
+
+impl Signal {
+    /// Returns the number of the signal.
+    pub fn as_raw(self) -> c_int {
+        self as c_int
+    }
+}
@@ -7,12 +7,151 @@
 //!
 //! C header: [`include/linux/jiffies.h`](srctree/include/linux/jiffies.h).
 //! C header: [`include/linux/ktime.h`](srctree/include/linux/ktime.h).
+//! C header: [`include/linux/timekeeping.h`](srctree/include/linux/timekeeping.h).
+
+use crate::task::MAX_SCHEDULE_TIMEOUT;
+use core::{
+    cmp::Ordering,
+    ffi::{c_long, c_ulong},
+    marker::PhantomData,
+};
+
+pub mod delay;
+pub mod hrtimer;
+mod timer;
+
+pub use timer::{impl_has_timer, new_timer, HasTimer, Timer, TimerItem};
+
+/// The number of nanoseconds per microsecond.
+pub const NSEC_PER_USEC: i64 = bindings::NSEC_PER_USEC as i64;
 
 /// The number of nanoseconds per millisecond.
 pub const NSEC_PER_MSEC: i64 = bindings::NSEC_PER_MSEC as i64;
 
-/// The time unit of Linux kernel. One jiffy equals (1/HZ) second.
-pub type Jiffies = core::ffi::c_ulong;
+/// The number of nanoseconds per second.
+pub const NSEC_PER_SEC: i64 = bindings::NSEC_PER_SEC as i64;
+
+/// A number of jiffies, the time unit of the Linux kernel. One jiffy equals (1/HZ) second.
+///
+/// It is either a duration, or a point in time as given by [`Jiffies::now`]. The counter wraps
+/// around, so points in time cannot be compared with the usual operators. Instead, use
+/// [`Jiffies::is_after`] and the similar methods, which handle the wraparound like the C
+/// `time_after` macros. For the same reason, the arithmetic operators wrap around.
+///
+/// # Examples
+///
+/// ```
+/// use kernel::time::Jiffies;
+///
+/// let start = Jiffies::from_raw(core::ffi::c_ulong::MAX - 1);
+/// let end = start + Jiffies::from_raw(3);
+///
+/// // `end` is after `start`, even though the counter wrapped around.
+/// assert!(end.is_after(start));
+/// assert!(start.is_before(end));
+/// assert!(!start.is_after_eq(end));
+/// assert_eq!(end - start, Jiffies::from_raw(3));
+/// ```
+#[repr(transparent)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Jiffies(c_ulong);
+
+impl Jiffies {
+    /// Zero jiffies.
+    pub const ZERO: Self = Self(0);
+
+    /// Creates a `Jiffies` from a raw number of jiffies.
+    #[inline]
+    pub const fn from_raw(jiffies: c_ulong) -> Self {
+        Self(jiffies)
+    }
+
+    /// Returns the raw number of jiffies.
+    #[inline]
+    pub const fn as_raw(self) -> c_ulong {
+        self.0
+    }
+
+    /// Returns the current value of the jiffies counter.
+    #[inline]
+    pub fn now() -> Self {
+        // SAFETY: `jiffies` is a C global, which is only updated by the timer interrupt, so a
+        // volatile read is enough (like `READ_ONCE`).
+        Self(unsafe { core::ptr::read_volatile(core::ptr::addr_of!(bindings::jiffies)) })
+    }
+
+    /// Converts milliseconds to jiffies, rounding up.
+    #[inline]
+    pub fn from_msecs(msecs: Msecs) -> Self {
+        msecs_to_jiffies(msecs)
+    }
+
+    /// Converts jiffies to milliseconds.
+    #[inline]
+    pub fn to_msecs(self) -> Msecs {
+        // SAFETY: The `jiffies_to_msecs` function is always safe to call no matter what the
+        // argument is.
+        unsafe { bindings::jiffies_to_msecs(self.0) }
+    }
+
+    /// Returns whether `self` is after `other` (`time_after`).
+    ///
+    /// It is correct as long as both are less than `c_ulong::MAX / 2` jiffies apart.
+    #[inline]
+    pub fn is_after(self, other: Self) -> bool {
+        (other.0.wrapping_sub(self.0) as c_long) < 0
+    }
+
+    /// Returns whether `self` is after or equal to `other` (`time_after_eq`).
+    #[inline]
+    pub fn is_after_eq(self, other: Self) -> bool {
+        (self.0.wrapping_sub(other.0) as c_long) >= 0
+    }
+
+    /// Returns whether `self` is before `other` (`time_before`).
+    #[inline]
+    pub fn is_before(self, other: Self) -> bool {
+        other.is_after(self)
+    }
+
+    /// Returns whether `self` is before or equal to `other` (`time_before_eq`).
+    #[inline]
+    pub fn is_before_eq(self, other: Self) -> bool {
+        other.is_after_eq(self)
+    }
+}
+
+impl core::ops::Add for Jiffies {
+    type Output = Jiffies;
+
+    #[inline]
+    fn add(self, other: Jiffies) -> Jiffies {
+        Self(self.0.wrapping_add(other.0))
+    }
+}
+
+impl core::ops::AddAssign for Jiffies {
+    #[inline]
+    fn add_assign(&mut self, other: Jiffies) {
+        *self = *self + other;
+    }
+}
+
+impl core::ops::Sub for Jiffies {
+    type Output = Jiffies;
+
+    #[inline]
+    fn sub(self, other: Jiffies) -> Jiffies {
+        Self(self.0.wrapping_sub(other.0))
+    }
+}
+
+impl core::ops::SubAssign for Jiffies {
+    #[inline]
+    fn sub_assign(&mut self, other: Jiffies) {
+        *self = *self - other;
+    }
+}
 
 /// The millisecond time unit.
 pub type Msecs = core::ffi::c_uint;
@@ -22,7 +161,48 @@ pub type Msecs = core::ffi::c_uint;
 pub fn msecs_to_jiffies(msecs: Msecs) -> Jiffies {
     // SAFETY: The `__msecs_to_jiffies` function is always safe to call no
     // matter what the argument is.
-    unsafe { bindings::__msecs_to_jiffies(msecs) }
+    Jiffies(unsafe { bindings::__msecs_to_jiffies(msecs) })
+}
+
+/// Converts a [`Delta`] to a timeout in jiffies for the scheduler.
+///
+/// [`Delta::MAX`] is converted to `MAX_SCHEDULE_TIMEOUT`, which means waiting forever. Negative
+/// deltas are treated as zero, and the other deltas are clamped to just below
+/// `MAX_SCHEDULE_TIMEOUT`, so that they are not mistaken for it.
+pub(crate) fn delta_to_timeout(delta: Delta) -> Jiffies {
+    if delta == Delta::MAX {
+        return Jiffies(MAX_SCHEDULE_TIMEOUT as c_ulong);
+    }
+
+    let nsecs = u64::try_from(delta.as_nanos()).unwrap_or(0);
+    // SAFETY: `nsecs_to_jiffies64` is always safe to call no matter what the argument is.
+    let jiffies = unsafe { bindings::nsecs_to_jiffies64(nsecs) };
+    // The clamped value fits in a `c_ulong`, even on 32-bit.
+    Jiffies(jiffies.min(MAX_SCHEDULE_TIMEOUT as u64 - 1) as c_ulong)
+}
+
+/// Converts a number of jiffies to a [`Delta`].
+///
+/// `MAX_SCHEDULE_TIMEOUT`, which means waiting forever, is converted to [`Delta::MAX`]. So are
+/// the numbers of jiffies that do not fit in a [`Delta`].
+pub(crate) fn jiffies_to_delta(jiffies: Jiffies) -> Delta {
+    if jiffies.0 == MAX_SCHEDULE_TIMEOUT as c_ulong {
+        return Delta::MAX;
+    }
+
+    // `jiffies64_to_nsecs` wraps around on overflow, so check that the multiplication fits
+    // first: `max` is the largest number of jiffies that converts to at most `i64::MAX`
+    // nanoseconds.
+    // SAFETY: `nsecs_to_jiffies64` is always safe to call no matter what the argument is.
+    let max = unsafe { bindings::nsecs_to_jiffies64(i64::MAX as u64) };
+    let jiffies = u64::from(jiffies.0);
+    if jiffies > max {
+        return Delta::MAX;
+    }
+
+    // SAFETY: `jiffies64_to_nsecs` is always safe to call no matter what the argument is.
+    let nsecs = unsafe { bindings::jiffies64_to_nsecs(jiffies) };
+    Delta::from_nanos(i64::try_from(nsecs).unwrap_or(i64::MAX))
 }
 
 /// A Rust wrapper around a `ktime_t`.
@@ -39,6 +219,12 @@ impl Ktime {
         Self { inner }
     }
 
+    /// Create a `Ktime` from a number of nanoseconds.
+    #[inline]
+    pub const fn from_ns(ns: i64) -> Self {
+        Self { inner: ns }
+    }
+
     /// Get the current time using `CLOCK_MONOTONIC`.
     #[inline]
     pub fn ktime_get() -> Self {
@@ -81,3 +267,294 @@ impl core::ops::Sub for Ktime {
         }
     }
 }
+
+/// A span of time with nanosecond resolution.
+///
+/// It may be negative, for example when it is the difference between two instants that are not
+/// in order. Unlike [`core::time::Duration`], it has the same representation as the C `ktime_t`
+/// deltas, so it converts to them without loss.
+///
+/// The constructors saturate on overflow, and the arithmetic is checked.
+///
+/// # Examples
+///
+/// ```
+/// use kernel::time::Delta;
+///
+/// let delta = Delta::from_millis(1500);
+/// assert_eq!(delta.as_secs(), 1);
+/// assert_eq!(delta.as_micros(), 1_500_000);
+///
+/// let sum = delta.checked_add(Delta::from_micros(500)).unwrap();
+/// assert_eq!(sum.as_nanos(), 1_500_500_000);
+/// assert!(Delta::ZERO.checked_sub(sum).unwrap().is_negative());
+///
+/// assert_eq!(Delta::from_secs(i64::MAX), Delta::MAX);
+/// assert!(Delta::MAX.checked_add(Delta::from_nanos(1)).is_none());
+/// ```
+#[repr(transparent)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub struct Delta {
+    nanos: i64,
+}
+
+impl Delta {
+    /// A span of zero length.
+    pub const ZERO: Self = Self { nanos: 0 };
+
+    /// The largest representable span, about 292 years.
+    pub const MAX: Self = Self { nanos: i64::MAX };
+
+    /// The smallest (most negative) representable span.
+    pub const MIN: Self = Self { nanos: i64::MIN };
+
+    /// Creates a [`Delta`] from a number of nanoseconds.
+    #[inline]
+    pub const fn from_nanos(nanos: i64) -> Self {
+        Self { nanos }
+    }
+
+    /// Creates a [`Delta`] from a number of microseconds, saturating on overflow.
+    #[inline]
+    pub const fn from_micros(micros: i64) -> Self {
+        Self::from_nanos(micros.saturating_mul(NSEC_PER_USEC))
+    }
+
+    /// Creates a [`Delta`] from a number of milliseconds, saturating on overflow.
+    #[inline]
+    pub const fn from_millis(millis: i64) -> Self {
+        Self::from_nanos(millis.saturating_mul(NSEC_PER_MSEC))
+    }
+
+    /// Creates a [`Delta`] from a number of seconds, saturating on overflow.
+    #[inline]
+    pub const fn from_secs(secs: i64) -> Self {
+        Self::from_nanos(secs.saturating_mul(NSEC_PER_SEC))
+    }
+
+    /// Returns whether the span has zero length.
+    #[inline]
+    pub const fn is_zero(self) -> bool {
+        self.nanos == 0
+    }
+
+    /// Returns whether the span is negative.
+    #[inline]
+    pub const fn is_negative(self) -> bool {
+        self.nanos < 0
+    }
+
+    /// Returns the number of nanoseconds.
+    #[inline]
+    pub const fn as_nanos(self) -> i64 {
+        self.nanos
+    }
+
+    /// Returns the number of whole microseconds, rounded towards zero.
+    #[inline]
+    pub const fn as_micros(self) -> i64 {
+        self.nanos / NSEC_PER_USEC
+    }
+
+    /// Returns the number of whole milliseconds, rounded towards zero.
+    #[inline]
+    pub const fn as_millis(self) -> i64 {
+        self.nanos / NSEC_PER_MSEC
+    }
+
+    /// Returns the number of whole seconds, rounded towards zero.
+    #[inline]
+    pub const fn as_secs(self) -> i64 {
+        self.nanos / NSEC_PER_SEC
+    }
+
+    /// Adds `other`, returning [`None`] on overflow.
+    #[inline]
+    pub const fn checked_add(self, other: Self) -> Option<Self> {
+        match self.nanos.checked_add(other.nanos) {
+            Some(nanos) => Some(Self::from_nanos(nanos)),
+            None => None,
+        }
+    }
+
+    /// Subtracts `other`, returning [`None`] on overflow.
+    #[inline]
+    pub const fn checked_sub(self, other: Self) -> Option<Self> {
+        match self.nanos.checked_sub(other.nanos) {
+            Some(nanos) => Some(Self::from_nanos(nanos)),
+            None => None,
+        }
+    }
+
+    /// Multiplies by `factor`, returning [`None`] on overflow.
+    #[inline]
+    pub const fn checked_mul(self, factor: i64) -> Option<Self> {
+        match self.nanos.checked_mul(factor) {
+            Some(nanos) => Some(Self::from_nanos(nanos)),
+            None => None,
+        }
+    }
+}
+
+/// A clock that [`Instant`]s are measured with.
+///
+/// It is implemented by [`Monotonic`], [`BootTime`], [`RealTime`] and [`Tai`].
+pub trait ClockSource {
+    /// The C clock ID (`CLOCK_*`).
+    const ID: bindings::clockid_t;
+
+    /// Returns the current time of the clock.
+    fn ktime_get() -> bindings::ktime_t;
+}
+
+/// The monotonic clock (`CLOCK_MONOTONIC`).
+///
+/// It counts the time since boot, except while the system is suspended. It is not affected by
+/// changes of the system time, so it is the right clock to measure elapsed time.
+pub struct Monotonic;
+
+impl ClockSource for Monotonic {
+    const ID: bindings::clockid_t = bindings::CLOCK_MONOTONIC as bindings::clockid_t;
+
+    fn ktime_get() -> bindings::ktime_t {
+        // SAFETY: It is always safe to call `ktime_get` outside of NMI context.
+        unsafe { bindings::ktime_get() }
+    }
+}
+
+/// The boot time clock (`CLOCK_BOOTTIME`).
+///
+/// It is like [`Monotonic`], except that it also counts the time while the system is suspended.
+pub struct BootTime;
+
+impl ClockSource for BootTime {
+    const ID: bindings::clockid_t = bindings::CLOCK_BOOTTIME as bindings::clockid_t;
+
+    fn ktime_get() -> bindings::ktime_t {
+        // SAFETY: It is always safe to call `ktime_get_with_offset` outside of NMI context.
+        unsafe { bindings::ktime_get_with_offset(bindings::tk_offsets_TK_OFFS_BOOT) }
+    }
+}
+
+/// The wall clock (`CLOCK_REALTIME`).
+///
+/// It follows the changes of the system time, which can jump forwards or backwards, so it should
+/// not be used to measure elapsed time.
+pub struct RealTime;
+
+impl ClockSource for RealTime {
+    const ID: bindings::clockid_t = bindings::CLOCK_REALTIME as bindings::clockid_t;
+
+    fn ktime_get() -> bindings::ktime_t {
+        // SAFETY: It is always safe to call `ktime_get_with_offset` outside of NMI context.
+        unsafe { bindings::ktime_get_with_offset(bindings::tk_offsets_TK_OFFS_REAL) }
+    }
+}
+
+/// The International Atomic Time clock (`CLOCK_TAI`).
+///
+/// It is like [`RealTime`], except that it does not have leap seconds.
+pub struct Tai;
+
+impl ClockSource for Tai {
+    const ID: bindings::clockid_t = bindings::CLOCK_TAI as bindings::clockid_t;
+
+    fn ktime_get() -> bindings::ktime_t {
+        // SAFETY: It is always safe to call `ktime_get_with_offset` outside of NMI context.
+        unsafe { bindings::ktime_get_with_offset(bindings::tk_offsets_TK_OFFS_TAI) }
+    }
+}
+
+/// A point in time, as measured by the clock `C`.
+///
+/// Instants of different clocks have different types, so they cannot be mixed up. The difference
+/// between two instants of the same clock is a [`Delta`].
+///
+/// # Examples
+///
+/// ```
+/// use kernel::time::{BootTime, Delta, Instant, Monotonic};
+///
+/// let start = Instant::<Monotonic>::now();
+/// let boot = Instant::<BootTime>::now();
+///
+/// assert!(!start.elapsed().is_negative());
+/// assert!(Instant::<Monotonic>::now() >= start);
+/// assert!(Instant::<Monotonic>::now() - start >= Delta::ZERO);
+///
+/// // The boot time clock also counts the time while suspended.
+/// assert!(boot.as_nanos() >= start.as_nanos());
+/// ```
+pub struct Instant<C: ClockSource> {
+    inner: bindings::ktime_t,
+    _c: PhantomData<C>,
+}
+
+impl<C: ClockSource> Instant<C> {
+    /// Returns the current time of the clock.
+    #[inline]
+    pub fn now() -> Self {
+        Self {
+            inner: C::ktime_get(),
+            _c: PhantomData,
+        }
+    }
+
+    /// Returns the time elapsed since `self`.
+    #[inline]
+    pub fn elapsed(&self) -> Delta {
+        Self::now() - *self
+    }
+
+    /// Returns the number of nanoseconds since the epoch of the clock.
+    #[inline]
+    pub fn as_nanos(&self) -> i64 {
+        self.inner
+    }
+
+    /// Returns the instant `delta` after `self`, or [`None`] on overflow.
+    #[inline]
+    pub fn checked_add(&self, delta: Delta) -> Option<Self> {
+        Some(Self {
+            inner: self.inner.checked_add(delta.as_nanos())?,
+            _c: PhantomData,
+        })
+    }
+}
+
+impl<C: ClockSource> Clone for Instant<C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C: ClockSource> Copy for Instant<C> {}
+
+impl<C: ClockSource> PartialEq for Instant<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<C: ClockSource> Eq for Instant<C> {}
+
+impl<C: ClockSource> PartialOrd for Instant<C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<C: ClockSource> Ord for Instant<C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.inner.cmp(&other.inner)
+    }
+}
+
+impl<C: ClockSource> core::ops::Sub for Instant<C> {
+    type Output = Delta;
+
+    #[inline]
+    fn sub(self, other: Self) -> Delta {
+        Delta::from_nanos(self.inner.saturating_sub(other.inner))
+    }
+}
@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Delays and sleeping.
+//!
+//! The functions of this module put the current thread to sleep, so they may only be called in
+//! contexts that can sleep. For timeouts that do not block the thread, see [`super::Timer`] and
+//! [`super::hrtimer`].
+//!
+//! C header: [`include/linux/delay.h`](srctree/include/linux/delay.h)
+
+use super::Delta;
+use crate::task::TASK_UNINTERRUPTIBLE;
+use core::ffi::{c_uint, c_ulong};
+
+/// Returns the number of microseconds of `delta`, rounded up.
+///
+/// Negative deltas are treated as zero.
+fn delta_to_usecs(delta: Delta) -> c_ulong {
+    let usecs = delta.as_micros() + i64::from(delta.as_nanos() % 1000 > 0);
+    c_ulong::try_from(usecs.max(0)).unwrap_or(c_ulong::MAX)
+}
+
+/// Sleeps for at least `delta` (`fsleep`).
+///
+/// It picks the most appropriate way to sleep for `delta`, which may be a busy wait for very short
+/// delays, so it is the recommended way to sleep when there is no specific requirement.
+///
+/// # Examples
+///
+/// ```
+/// use kernel::time::{delay::fsleep, Delta, Instant, Monotonic};
+///
+/// let start = Instant::<Monotonic>::now();
+/// fsleep(Delta::from_micros(100));
+/// assert!(start.elapsed() >= Delta::from_micros(100));
+/// ```
+pub fn fsleep(delta: Delta) {
+    // SAFETY: `fsleep` is always safe to call in contexts that can sleep.
+    unsafe { bindings::fsleep(delta_to_usecs(delta)) }
+}
+
+/// Sleeps for at least `delta`, with millisecond granularity (`msleep`).
+///
+/// The delay is rounded up to the next millisecond, and the actual sleep is usually longer, by up
+/// to a jiffy, so it is only suitable for delays of several milliseconds.
+pub fn msleep(delta: Delta) {
+    let msecs = delta_to_usecs(delta).div_ceil(1000);
+    // SAFETY: `msleep` is always safe to call in contexts that can sleep.
+    unsafe { bindings::msleep(c_uint::try_from(msecs).unwrap_or(c_uint::MAX)) }
+}
+
+/// Sleeps for a time between `min` and `max` (`usleep_range`).
+///
+/// The range allows the wake up to be coalesced with other ones, so it should be as large as
+/// acceptable. It is meant for delays between about ten microseconds and a few milliseconds.
+pub fn usleep_range(min: Delta, max: Delta) {
+    let min = delta_to_usecs(min);
+    let max = delta_to_usecs(max).max(min);
+    // SAFETY: `usleep_range_state` is always safe to call in contexts that can sleep.
+    unsafe { bindings::usleep_range_state(min, max, TASK_UNINTERRUPTIBLE as c_uint) }
+}
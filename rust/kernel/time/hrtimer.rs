@@ -0,0 +1,621 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! High-resolution timers.
+//!
+//! This module allows Rust code to use the kernel's [`struct hrtimer`], which calls a function at
+//! a given time with nanosecond resolution.
+//!
+//! The timer is embedded in a struct as an [`HrTimer`] field, declared with the
+//! [`impl_has_hr_timer!`] macro, in the same way as the `work_struct` fields of work items. The
+//! struct implements [`TimerCallback`] to define what happens when the timer expires, and the
+//! timer is started through a pointer to it, an [`Arc`] or a [`Pin<Box>`], which implements
+//! [`TimerPointer`].
+//!
+//! Starting a timer returns a handle that keeps the struct alive while the timer is armed. The
+//! timer is cancelled when the handle is dropped.
+//!
+//! Timer callbacks run in hard interrupt context, so they must not sleep.
+//!
+//! # Examples
+//!
+//! The following example shows a timer that expires three times, once per millisecond:
+//!
+//! ```
+//! use kernel::sync::{new_completion, Arc, ArcBorrow, Atomic, Completion};
+//! use kernel::time::hrtimer::{
+//!     impl_has_hr_timer, HrTimer, HrTimerCallbackContext, RelativeMode, TimerCallback,
+//!     TimerPointer, TimerRestart,
+//! };
+//! use kernel::time::{Delta, Monotonic};
+//!
+//! #[pin_data]
+//! struct Ticker {
+//!     ticks: Atomic<i32>,
+//!     #[pin]
+//!     done: Completion,
+//!     #[pin]
+//!     timer: HrTimer<Ticker>,
+//! }
+//!
+//! impl_has_hr_timer! {
+//!     impl HasHrTimer<Self> for Ticker { self.timer }
+//! }
+//!
+//! impl TimerCallback for Ticker {
+//!     type Pointer = Arc<Self>;
+//!     type Mode = RelativeMode<Monotonic>;
+//!
+//!     fn run(
+//!         this: ArcBorrow<'_, Self>,
+//!         mut ctx: HrTimerCallbackContext<'_, Self>,
+//!     ) -> TimerRestart {
+//!         if this.ticks.add_return(1) < 3 {
+//!             ctx.forward_now(Delta::from_millis(1));
+//!             TimerRestart::Restart
+//!         } else {
+//!             this.done.complete_all();
+//!             TimerRestart::NoRestart
+//!         }
+//!     }
+//! }
+//!
+//! let ticker = Arc::pin_init(pin_init!(Ticker {
+//!     ticks: Atomic::new(0),
+//!     done <- new_completion!("Ticker::done"),
+//!     timer <- HrTimer::new(),
+//! }), GFP_KERNEL)?;
+//!
+//! let mut handle = ticker.clone().start(Delta::from_millis(1));
+//! ticker.done.wait();
+//! assert_eq!(ticker.ticks.load(), 3);
+//!
+//! // The timer is not armed anymore.
+//! assert!(!handle.cancel());
+//! # Ok::<(), Error>(())
+//! ```
+//!
+//! C header: [`include/linux/hrtimer.h`](srctree/include/linux/hrtimer.h)
+//!
+//! [`struct hrtimer`]: srctree/include/linux/hrtimer_types.h
+//! [`Pin<Box>`]: Box
+
+use super::{ClockSource, Delta, Instant};
+use crate::{
+    prelude::*,
+    sync::{Arc, ArcBorrow},
+    types::Opaque,
+};
+use core::{marker::PhantomData, ptr::NonNull};
+
+/// The expiry time of a timer.
+///
+/// It is implemented by [`Instant`] for absolute timers, and by [`Delta`] for relative ones.
+pub trait HrTimerExpires {
+    /// Returns the expiry time as a number of nanoseconds, as expected by C.
+    fn as_nanos(&self) -> i64;
+}
+
+impl<C: ClockSource> HrTimerExpires for Instant<C> {
+    #[inline]
+    fn as_nanos(&self) -> i64 {
+        Instant::<C>::as_nanos(self)
+    }
+}
+
+impl HrTimerExpires for Delta {
+    #[inline]
+    fn as_nanos(&self) -> i64 {
+        Delta::as_nanos(*self)
+    }
+}
+
+/// The clock that a timer is based on, and how its expiry time is interpreted.
+///
+/// It is implemented by [`AbsoluteMode`] and [`RelativeMode`].
+pub trait HrTimerMode {
+    /// The clock of the timer.
+    type Clock: ClockSource;
+
+    /// The type of the expiry time.
+    type Expires: HrTimerExpires;
+
+    /// The C mode (`HRTIMER_MODE_*`).
+    const MODE: bindings::hrtimer_mode;
+}
+
+/// The expiry time is an [`Instant`] of the clock `C` (`HRTIMER_MODE_ABS`).
+pub struct AbsoluteMode<C: ClockSource>(PhantomData<C>);
+
+impl<C: ClockSource> HrTimerMode for AbsoluteMode<C> {
+    type Clock = C;
+    type Expires = Instant<C>;
+
+    const MODE: bindings::hrtimer_mode = bindings::hrtimer_mode_HRTIMER_MODE_ABS;
+}
+
+/// The expiry time is a [`Delta`] from the current time of the clock `C` (`HRTIMER_MODE_REL`).
+pub struct RelativeMode<C: ClockSource>(PhantomData<C>);
+
+impl<C: ClockSource> HrTimerMode for RelativeMode<C> {
+    type Clock = C;
+    type Expires = Delta;
+
+    const MODE: bindings::hrtimer_mode = bindings::hrtimer_mode_HRTIMER_MODE_REL;
+}
+
+/// What happens to a timer after its callback returns.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimerRestart {
+    /// The timer is not armed anymore (`HRTIMER_NORESTART`).
+    NoRestart,
+
+    /// The timer is armed again with its expiry time (`HRTIMER_RESTART`).
+    ///
+    /// The expiry time should be moved forward with [`HrTimerCallbackContext::forward_now`]
+    /// first, otherwise the timer expires again immediately.
+    Restart,
+}
+
+impl TimerRestart {
+    fn into_raw(self) -> bindings::hrtimer_restart {
+        match self {
+            Self::NoRestart => bindings::hrtimer_restart_HRTIMER_NORESTART,
+            Self::Restart => bindings::hrtimer_restart_HRTIMER_RESTART,
+        }
+    }
+}
+
+/// A high-resolution timer.
+///
+/// Wraps the kernel's C `struct hrtimer`, along with the mode that it is started with.
+///
+/// This is a helper type used to associate a `struct hrtimer` with the [`TimerCallback`] that
+/// uses it. The clock and the mode of the timer are given by [`TimerCallback::Mode`].
+///
+/// # Invariants
+///
+/// `timer` was initialised with the clock of the timer, `mode`, and the [`run`] function of the
+/// pointer type of `T` as its function.
+///
+/// [`run`]: TimerPointer::run
+#[pin_data]
+#[repr(C)]
+pub struct HrTimer<T: ?Sized> {
+    #[pin]
+    timer: Opaque<bindings::hrtimer>,
+    mode: bindings::hrtimer_mode,
+    _inner: PhantomData<T>,
+}
+
+// SAFETY: Kernel timers are usable from any thread.
+//
+// We do not need to constrain `T` since the timer does not actually contain a `T`.
+unsafe impl<T: ?Sized> Send for HrTimer<T> {}
+// SAFETY: Kernel timers are usable from any thread.
+//
+// We do not need to constrain `T` since the timer does not actually contain a `T`.
+unsafe impl<T: ?Sized> Sync for HrTimer<T> {}
+
+impl<T: ?Sized> HrTimer<T> {
+    /// Creates a new instance of [`HrTimer`], with the clock and the mode of
+    /// [`TimerCallback::Mode`].
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new() -> impl PinInit<Self>
+    where
+        T: TimerCallback,
+    {
+        let clock = <<T::Mode as HrTimerMode>::Clock as ClockSource>::ID;
+        let mode = <T::Mode as HrTimerMode>::MODE;
+
+        // INVARIANT: The timer is initialised with `clock`, `mode` and the `run` function.
+        pin_init!(Self {
+            timer <- Opaque::ffi_init(move |slot: *mut bindings::hrtimer| {
+                // SAFETY: `slot` points to memory that is valid for writes.
+                unsafe { bindings::hrtimer_init(slot, clock, mode) };
+
+                // SAFETY: `slot` was just initialised, and the `TimerPointer` implementation
+                // promises that `run` can be used as the timer function.
+                unsafe { (*slot).function = Some(T::Pointer::run) };
+            }),
+            mode,
+            _inner: PhantomData,
+        })
+    }
+
+    /// Get a pointer to the inner `struct hrtimer`.
+    ///
+    /// # Safety
+    ///
+    /// The provided pointer must not be dangling and must be properly aligned. (But the memory
+    /// need not be initialized.)
+    #[inline]
+    pub unsafe fn raw_get(ptr: *const Self) -> *mut bindings::hrtimer {
+        // SAFETY: The caller promises that the pointer is aligned and not dangling.
+        unsafe { Opaque::raw_get(core::ptr::addr_of!((*ptr).timer)) }
+    }
+
+    /// Cancels the timer if it is armed, and waits for its callback to finish if it is running
+    /// (`hrtimer_cancel`).
+    ///
+    /// Returns whether the timer was armed. A callback that restarts the timer is cancelled as
+    /// well. It may spin, and must not be called from the callback of the timer.
+    pub fn cancel(&self) -> bool {
+        // SAFETY: `timer` was initialised by the constructor.
+        unsafe { bindings::hrtimer_cancel(self.timer.get()) != 0 }
+    }
+}
+
+/// Defines the callback of a timer.
+///
+/// This trait is implemented by structs that have an [`HrTimer`] field, declared with
+/// [`impl_has_hr_timer!`].
+pub trait TimerCallback {
+    /// The pointer type that the timer is started with. This will typically be `Arc<Self>` or
+    /// `Pin<Box<Self>>`.
+    type Pointer: TimerPointer;
+
+    /// The clock of the timer, and whether it is started with an [`Instant`] or a [`Delta`].
+    type Mode: HrTimerMode;
+
+    /// The method that is called when the timer expires.
+    ///
+    /// It runs in hard interrupt context, so it must not sleep. Its return value tells whether the
+    /// timer is armed again.
+    fn run(
+        this: <Self::Pointer as TimerPointer>::Borrowed<'_>,
+        ctx: HrTimerCallbackContext<'_, Self>,
+    ) -> TimerRestart
+    where
+        Self: Sized;
+}
+
+/// A pointer type that a timer can be started with.
+///
+/// This trait is implemented by `Pin<Box<T>>` and [`Arc<T>`]. Starting a timer returns a handle
+/// that owns the pointer, and cancels the timer when dropped, so that the struct containing it
+/// outlives the timer.
+///
+/// # Safety
+///
+/// Implementers must ensure that the struct pointed at remains valid while the timer is armed or
+/// its callback runs, and that [`run`] is only used with timers of the type of the pointer.
+///
+/// [`run`]: TimerPointer::run
+pub unsafe trait TimerPointer: Sized {
+    /// The type that the callback receives to access the struct.
+    type Borrowed<'a>;
+
+    /// The type that [`TimerPointer::start`] returns.
+    type Handle;
+
+    /// The type of the expiry time, given by the [`HrTimerMode`] of the timer.
+    type Expires: HrTimerExpires;
+
+    /// Starts the timer, so that it expires at `expires` (`hrtimer_start`).
+    ///
+    /// `expires` is an [`Instant`] of the clock of the timer, or a [`Delta`] from now if the timer
+    /// is relative. A timer that is already armed is armed again with the new expiry time.
+    fn start(self, expires: Self::Expires) -> Self::Handle;
+
+    /// The function that is called when the timer expires.
+    ///
+    /// # Safety
+    ///
+    /// The provided `hrtimer` pointer must point at the timer of a struct that was started with
+    /// [`TimerPointer::start`], whose handle still exists.
+    unsafe extern "C" fn run(ptr: *mut bindings::hrtimer) -> bindings::hrtimer_restart;
+}
+
+/// The context in which the callback of a timer runs.
+///
+/// It allows the callback to move the expiry time forward before restarting the timer.
+///
+/// # Invariants
+///
+/// `timer` is the timer whose callback is running, for the lifetime `'a`.
+pub struct HrTimerCallbackContext<'a, T> {
+    timer: NonNull<bindings::hrtimer>,
+    _p: PhantomData<&'a HrTimer<T>>,
+}
+
+impl<'a, T: TimerCallback> HrTimerCallbackContext<'a, T> {
+    /// Creates the context of the callback of `timer`.
+    ///
+    /// # Safety
+    ///
+    /// `timer` must be the timer whose callback is running, for the lifetime `'a`.
+    unsafe fn from_raw(timer: *mut bindings::hrtimer) -> Self {
+        // INVARIANT: The safety requirements ensure that `timer` is the running timer.
+        Self {
+            // SAFETY: The safety requirements ensure that `timer` is valid.
+            timer: unsafe { NonNull::new_unchecked(timer) },
+            _p: PhantomData,
+        }
+    }
+
+    /// Moves the expiry time forward by a multiple of `interval`, so that it is after `now`
+    /// (`hrtimer_forward`).
+    ///
+    /// Returns the number of intervals that were added, that is, the number of missed expiries
+    /// plus one.
+    pub fn forward(
+        &mut self,
+        now: Instant<<T::Mode as HrTimerMode>::Clock>,
+        interval: Delta,
+    ) -> u64 {
+        // SAFETY: By the type invariants, `timer` is valid and its callback is running, so it is
+        // not enqueued.
+        unsafe {
+            bindings::hrtimer_forward(self.timer.as_ptr(), now.as_nanos(), interval.as_nanos())
+        }
+    }
+
+    /// Moves the expiry time forward by a multiple of `interval`, so that it is after the current
+    /// time of the clock of the timer (`hrtimer_forward_now`).
+    ///
+    /// Returns the number of intervals that were added, like [`HrTimerCallbackContext::forward`].
+    pub fn forward_now(&mut self, interval: Delta) -> u64 {
+        // SAFETY: By the type invariants, `timer` is valid and its callback is running, so it is
+        // not enqueued.
+        unsafe { bindings::hrtimer_forward_now(self.timer.as_ptr(), interval.as_nanos()) }
+    }
+}
+
+/// Declares that a type has an [`HrTimer<T>`] field.
+///
+/// The intended way of using this trait is via the [`impl_has_hr_timer!`] macro.
+///
+/// # Safety
+///
+/// The [`OFFSET`] constant must be the offset of a field in `Self` of type [`HrTimer<T>`]. The
+/// methods on this trait must have exactly the behavior that the definitions given below have.
+///
+/// [`impl_has_hr_timer!`]: crate::impl_has_hr_timer
+/// [`OFFSET`]: HasHrTimer::OFFSET
+pub unsafe trait HasHrTimer<T> {
+    /// The offset of the [`HrTimer<T>`] field.
+    const OFFSET: usize;
+
+    /// Returns a pointer to the [`HrTimer<T>`] field.
+    ///
+    /// # Safety
+    ///
+    /// The provided pointer must point at a valid struct of type `Self`.
+    #[inline]
+    unsafe fn raw_get_timer(ptr: *const Self) -> *const HrTimer<T> {
+        // SAFETY: The caller promises that the pointer is valid.
+        unsafe { (ptr as *const u8).add(Self::OFFSET) as *const HrTimer<T> }
+    }
+
+    /// Returns a pointer to the struct containing the [`HrTimer<T>`] field.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must point at an [`HrTimer<T>`] field in a struct of type `Self`.
+    #[inline]
+    unsafe fn timer_container_of(ptr: *mut HrTimer<T>) -> *mut Self
+    where
+        Self: Sized,
+    {
+        // SAFETY: The caller promises that the pointer points at a field of the right type in the
+        // right kind of struct.
+        unsafe { (ptr as *mut u8).sub(Self::OFFSET) as *mut Self }
+    }
+
+    /// Starts the [`HrTimer<T>`] field, so that it expires at `expires`.
+    ///
+    /// # Safety
+    ///
+    /// The provided pointer must point at a valid struct of type `Self`, which must remain valid
+    /// while the timer is armed or its callback runs.
+    unsafe fn start<E: HrTimerExpires>(ptr: *const Self, expires: E) {
+        // SAFETY: The caller promises that the pointer is valid.
+        let timer = unsafe { Self::raw_get_timer(ptr) };
+        // SAFETY: `timer` points at a valid `HrTimer<T>`, which was initialised by its constructor,
+        // and the caller promises that it remains valid while it is armed.
+        unsafe {
+            bindings::hrtimer_start_range_ns(
+                HrTimer::raw_get(timer),
+                expires.as_nanos(),
+                0,
+                (*timer).mode,
+            )
+        };
+    }
+}
+
+/// Used to safely implement the [`HasHrTimer<T>`] trait.
+///
+/// # Examples
+///
+/// ```
+/// use kernel::time::hrtimer::{impl_has_hr_timer, HrTimer};
+///
+/// struct MyStruct<T> {
+///     value: T,
+///     timer: HrTimer<MyStruct<T>>,
+/// }
+///
+/// impl_has_hr_timer! {
+///     impl{T} HasHrTimer<MyStruct<T>> for MyStruct<T> { self.timer }
+/// }
+/// ```
+#[macro_export]
+macro_rules! impl_has_hr_timer {
+    ($(impl$({$($generics:tt)*})?
+       HasHrTimer<$timer_type:ty>
+       for $self:ty
+       { self.$field:ident }
+    )*) => {$(
+        // SAFETY: The implementation of `raw_get_timer` only compiles if the field has the right
+        // type.
+        unsafe impl$(<$($generics)+>)? $crate::time::hrtimer::HasHrTimer<$timer_type> for $self {
+            const OFFSET: usize = ::core::mem::offset_of!(Self, $field) as usize;
+
+            #[inline]
+            unsafe fn raw_get_timer(
+                ptr: *const Self,
+            ) -> *const $crate::time::hrtimer::HrTimer<$timer_type> {
+                // SAFETY: The caller promises that the pointer is not dangling.
+                unsafe {
+                    ::core::ptr::addr_of!((*ptr).$field)
+                }
+            }
+        }
+    )*};
+}
+pub use impl_has_hr_timer;
+
+/// A handle to a timer started with an [`Arc`].
+///
+/// It holds a reference to the struct, and cancels the timer when dropped.
+pub struct ArcTimerHandle<T>
+where
+    T: HasHrTimer<T>,
+{
+    inner: Arc<T>,
+}
+
+impl<T> ArcTimerHandle<T>
+where
+    T: HasHrTimer<T>,
+{
+    /// Cancels the timer, see [`HrTimer::cancel`].
+    pub fn cancel(&mut self) -> bool {
+        let ptr: *const T = &*self.inner;
+        // SAFETY: `ptr` points at the struct that the `Arc` holds.
+        unsafe { (*T::raw_get_timer(ptr)).cancel() }
+    }
+}
+
+impl<T> Drop for ArcTimerHandle<T>
+where
+    T: HasHrTimer<T>,
+{
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+// SAFETY: The handle holds a reference to the struct, so it remains valid until the timer is
+// cancelled by the destructor of the handle. `run` recovers the struct with `timer_container_of`,
+// which is only valid for the timers of structs of type `T`.
+unsafe impl<T> TimerPointer for Arc<T>
+where
+    T: TimerCallback<Pointer = Self>,
+    T: HasHrTimer<T>,
+    T: Send + Sync + 'static,
+{
+    type Borrowed<'a> = ArcBorrow<'a, T>;
+    type Handle = ArcTimerHandle<T>;
+    type Expires = <T::Mode as HrTimerMode>::Expires;
+
+    fn start(self, expires: Self::Expires) -> ArcTimerHandle<T> {
+        // SAFETY: Pointers into an `Arc` point at a valid value, which the handle keeps alive until
+        // the timer is cancelled.
+        unsafe { T::start(&*self, expires) };
+        ArcTimerHandle { inner: self }
+    }
+
+    unsafe extern "C" fn run(ptr: *mut bindings::hrtimer) -> bindings::hrtimer_restart {
+        // `HrTimer` is `repr(C)` and `timer` is its first field.
+        let timer_ptr = ptr.cast::<HrTimer<T>>();
+        // SAFETY: The timer was started by `start`, so it is the field of a struct of type `T`.
+        let data_ptr = unsafe { T::timer_container_of(timer_ptr) };
+        // SAFETY: The struct is in an `Arc`, and the handle holds a reference to it until the
+        // callback returns, since its destructor waits for the callback.
+        let this = unsafe { ArcBorrow::from_raw(data_ptr) };
+        // SAFETY: The callback of `ptr` is running until `run` returns.
+        let ctx = unsafe { HrTimerCallbackContext::from_raw(ptr) };
+
+        T::run(this, ctx).into_raw()
+    }
+}
+
+/// A handle to a timer started with a [`Pin<Box>`].
+///
+/// It owns the struct, and cancels the timer before freeing it when dropped.
+///
+/// # Invariants
+///
+/// `inner` comes from [`Box::into_raw`] on a pinned box.
+///
+/// [`Pin<Box>`]: Box
+pub struct BoxTimerHandle<T>
+where
+    T: HasHrTimer<T>,
+{
+    inner: NonNull<T>,
+}
+
+// SAFETY: The handle owns the struct, so it can be sent to another thread if the struct can.
+unsafe impl<T> Send for BoxTimerHandle<T> where T: HasHrTimer<T> + Send {}
+
+// SAFETY: Shared references to the handle only allow to cancel the timer, which can be done from
+// any thread.
+unsafe impl<T> Sync for BoxTimerHandle<T> where T: HasHrTimer<T> {}
+
+impl<T> BoxTimerHandle<T>
+where
+    T: HasHrTimer<T>,
+{
+    /// Cancels the timer, see [`HrTimer::cancel`].
+    pub fn cancel(&mut self) -> bool {
+        // SAFETY: By the type invariants, `inner` points at a valid struct.
+        unsafe { (*T::raw_get_timer(self.inner.as_ptr())).cancel() }
+    }
+}
+
+impl<T> Drop for BoxTimerHandle<T>
+where
+    T: HasHrTimer<T>,
+{
+    fn drop(&mut self) {
+        self.cancel();
+        // SAFETY: By the type invariants, `inner` comes from `Box::into_raw`, and the timer is not
+        // armed anymore.
+        drop(unsafe { Box::from_raw(self.inner.as_ptr()) });
+    }
+}
+
+// SAFETY: The handle owns the struct, and only frees it after cancelling the timer. `run` recovers
+// the struct with `timer_container_of`, which is only valid for the timers of structs of type `T`.
+unsafe impl<T> TimerPointer for Pin<Box<T>>
+where
+    T: TimerCallback<Pointer = Self>,
+    T: HasHrTimer<T>,
+    T: Send + Sync + 'static,
+{
+    type Borrowed<'a> = Pin<&'a T>;
+    type Handle = BoxTimerHandle<T>;
+    type Expires = <T::Mode as HrTimerMode>::Expires;
+
+    fn start(self, expires: Self::Expires) -> BoxTimerHandle<T> {
+        // SAFETY: We're not going to move `self` or any of its fields, so its okay to temporarily
+        // remove the `Pin` wrapper.
+        let ptr = Box::into_raw(unsafe { Pin::into_inner_unchecked(self) });
+        // SAFETY: Pointers into a `Box` point at a valid value, which the handle frees only after
+        // cancelling the timer.
+        unsafe { T::start(ptr, expires) };
+        // INVARIANT: `ptr` comes from `Box::into_raw` on a pinned box.
+        BoxTimerHandle {
+            // SAFETY: `Box::into_raw` never returns null.
+            inner: unsafe { NonNull::new_unchecked(ptr) },
+        }
+    }
+
+    unsafe extern "C" fn run(ptr: *mut bindings::hrtimer) -> bindings::hrtimer_restart {
+        // `HrTimer` is `repr(C)` and `timer` is its first field.
+        let timer_ptr = ptr.cast::<HrTimer<T>>();
+        // SAFETY: The timer was started by `start`, so it is the field of a struct of type `T`.
+        let data_ptr = unsafe { T::timer_container_of(timer_ptr) };
+        // SAFETY: The handle owns the struct and does not give access to it, and it is only freed
+        // after the callback returns, since the destructor of the handle waits for it. The box was
+        // already pinned when the timer was started.
+        let this = unsafe { Pin::new_unchecked(&*data_ptr) };
+        // SAFETY: The callback of `ptr` is running until `run` returns.
+        let ctx = unsafe { HrTimerCallbackContext::from_raw(ptr) };
+
+        T::run(this, ctx).into_raw()
+    }
+}
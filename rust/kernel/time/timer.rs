@@ -0,0 +1,349 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Timers with jiffy resolution.
+//!
+//! C header: [`include/linux/timer.h`](srctree/include/linux/timer.h)
+
+use super::Jiffies;
+use crate::{prelude::*, sync::Arc, sync::LockClassKey, types::Opaque};
+use core::{
+    marker::PhantomData,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Creates a [`Timer`] initialiser with the given name and a newly-created lock class.
+#[macro_export]
+macro_rules! new_timer {
+    ($($name:literal)?) => {
+        $crate::time::Timer::new($crate::optional_name!($($name)?), $crate::static_lock_class!())
+    };
+}
+pub use new_timer;
+
+/// Defines the method that is called when a [`Timer`] expires.
+///
+/// The timer is armed with an [`Arc`] to the struct that contains it, which the timer owns until
+/// it expires or is deleted.
+pub trait TimerItem {
+    /// The method that is called when the timer expires.
+    ///
+    /// It runs in softirq context, so it must not sleep. It may arm the timer again with
+    /// [`Timer::mod_timer`].
+    fn run(this: Arc<Self>);
+}
+
+/// A timer with jiffy resolution.
+///
+/// Wraps the kernel's C `struct timer_list`, which is coarser but cheaper than a
+/// [high-resolution timer]. It is usually used for timeouts that are expected to be deleted before
+/// they expire.
+///
+/// A field of this type is declared with the [`impl_has_timer!`] macro, and the timer is then
+/// armed with [`Timer::mod_timer`], which takes an [`Arc`] to the struct containing it.
+///
+/// # Invariants
+///
+/// `timer` was initialised with the [`TimerItem`] callback of `T`. While it is pending, it owns a
+/// reference to the [`Arc`] that contains it, which was passed to [`Timer::mod_timer`].
+///
+/// # Examples
+///
+/// The following example shows a watchdog that fires unless it is fed in time:
+///
+/// ```
+/// use kernel::sync::{new_completion, Arc, Completion};
+/// use kernel::time::{impl_has_timer, msecs_to_jiffies, new_timer, Jiffies, Timer, TimerItem};
+///
+/// #[pin_data]
+/// struct Watchdog {
+///     #[pin]
+///     fired: Completion,
+///     #[pin]
+///     timer: Timer<Watchdog>,
+/// }
+///
+/// impl_has_timer! {
+///     impl HasTimer<Self> for Watchdog { self.timer }
+/// }
+///
+/// impl TimerItem for Watchdog {
+///     fn run(this: Arc<Self>) {
+///         this.fired.complete_all();
+///     }
+/// }
+///
+/// let dog = Arc::pin_init(pin_init!(Watchdog {
+///     fired <- new_completion!("Watchdog::fired"),
+///     timer <- new_timer!("Watchdog::timer"),
+/// }), GFP_KERNEL)?;
+///
+/// assert!(!Timer::mod_timer(dog.clone(), Jiffies::now() + msecs_to_jiffies(1000)));
+///
+/// // The timer is pending, so it keeps its `Arc` and only the expiry time changes.
+/// assert!(Timer::mod_timer(dog.clone(), Jiffies::now() + msecs_to_jiffies(1)));
+/// dog.fired.wait();
+/// assert!(!dog.timer.del_timer_sync());
+///
+/// // Feeding the watchdog in time.
+/// Timer::mod_timer(dog.clone(), Jiffies::now() + msecs_to_jiffies(1000));
+/// assert!(dog.timer.del_timer_sync());
+///
+/// // Once shut down, the timer cannot be armed again.
+/// dog.timer.shutdown_sync();
+/// assert!(!Timer::mod_timer(dog.clone(), Jiffies::now()));
+/// assert!(!dog.timer.del_timer_sync());
+/// # Ok::<(), Error>(())
+/// ```
+///
+/// [high-resolution timer]: super::hrtimer::HrTimer
+#[pin_data]
+#[repr(C)]
+pub struct Timer<T: ?Sized> {
+    #[pin]
+    timer: Opaque<bindings::timer_list>,
+    shut_down: AtomicBool,
+    _inner: PhantomData<T>,
+}
+
+// SAFETY: Kernel timers are usable from any thread.
+//
+// We do not need to constrain `T` since the timer does not actually contain a `T`.
+unsafe impl<T: ?Sized> Send for Timer<T> {}
+// SAFETY: Kernel timers are usable from any thread.
+//
+// We do not need to constrain `T` since the timer does not actually contain a `T`.
+unsafe impl<T: ?Sized> Sync for Timer<T> {}
+
+impl<T: ?Sized> Timer<T> {
+    /// Get a pointer to the inner `timer_list`.
+    ///
+    /// # Safety
+    ///
+    /// The provided pointer must not be dangling and must be properly aligned. (But the memory
+    /// need not be initialized.)
+    #[inline]
+    pub unsafe fn raw_get(ptr: *const Self) -> *mut bindings::timer_list {
+        // SAFETY: The caller promises that the pointer is aligned and not dangling.
+        unsafe { Opaque::raw_get(core::ptr::addr_of!((*ptr).timer)) }
+    }
+}
+
+impl<T> Timer<T>
+where
+    T: TimerItem + HasTimer<T> + Send + Sync + 'static,
+{
+    /// Creates a new instance of [`Timer`].
+    #[inline]
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(name: &'static CStr, key: &'static LockClassKey) -> impl PinInit<Self> {
+        pin_init!(Self {
+            // SAFETY: `slot` is valid while the closure is called and both `name` and `key` have
+            // static lifetimes so they live indefinitely.
+            timer <- Opaque::ffi_init(|slot| unsafe {
+                bindings::init_timer_key(
+                    slot,
+                    Some(Self::run),
+                    0,
+                    name.as_char_ptr(),
+                    key.as_ptr(),
+                )
+            }),
+            shut_down: AtomicBool::new(false),
+            _inner: PhantomData,
+        })
+    }
+
+    /// Arms the timer of `this`, so that it expires at `expires` (`mod_timer`).
+    ///
+    /// If the timer was already pending, only its expiry time changes and `this` is dropped, since
+    /// the timer already owns a reference. Returns whether it was pending.
+    ///
+    /// It does nothing, and returns `false`, if the timer was shut down. It never blocks, so it may
+    /// be called from any context, including the callback of the timer.
+    pub fn mod_timer(this: Arc<T>, expires: Jiffies) -> bool {
+        // Keeps `timer` valid until the end, since the timer may expire and drop `this` as soon as
+        // it is armed.
+        let keep = this.clone();
+        // SAFETY: Pointers into an `Arc` point at a valid value.
+        let timer = unsafe { &*T::raw_get_timer(&*keep) };
+
+        if timer.shut_down.load(Ordering::SeqCst) {
+            return false;
+        }
+
+        let ptr = Arc::into_raw(this);
+        // SAFETY: `timer` was initialised by the constructor.
+        let pending = unsafe { bindings::mod_timer(timer.timer.get(), expires.as_raw()) } != 0;
+        if pending {
+            // SAFETY: The timer was already pending, so it owns another reference and we still own
+            // the pointer.
+            drop(unsafe { Arc::from_raw(ptr) });
+        }
+        // INVARIANT: Otherwise, the timer was armed and owns the reference.
+
+        // `shutdown_sync` may have set the flag after it was checked above, and deleted the timer
+        // before it was armed. Both sides use `SeqCst`, so either the check above sees the flag,
+        // or this one does and deletes the timer again. This does not wait for the callback, which
+        // may be running already, but sees the flag and does not call `TimerItem::run`. Waiting
+        // is left to `shutdown_sync`, so that this never blocks.
+        if timer.shut_down.load(Ordering::SeqCst) {
+            timer.del_timer();
+            return false;
+        }
+
+        pending
+    }
+
+    /// Deletes the timer if it is pending, and waits for its callback to finish if it is running
+    /// (`del_timer_sync`).
+    ///
+    /// If the timer was pending, the [`Arc`] it owns is dropped. Returns whether it was pending.
+    /// A callback that arms the timer again is deleted as well.
+    ///
+    /// It may sleep, and must not be called from the callback of the timer.
+    pub fn del_timer_sync(&self) -> bool {
+        // SAFETY: `timer` was initialised by the constructor.
+        if unsafe { bindings::timer_delete_sync(self.timer.get()) } == 0 {
+            return false;
+        }
+
+        self.release();
+        true
+    }
+
+    /// Deletes the timer if it is pending, without waiting for its callback (`timer_delete`).
+    fn del_timer(&self) {
+        // SAFETY: `timer` was initialised by the constructor.
+        if unsafe { bindings::timer_delete(self.timer.get()) } != 0 {
+            self.release();
+        }
+    }
+
+    /// Deletes the timer like [`Timer::del_timer_sync`], and prevents it from being armed again
+    /// (`timer_shutdown_sync`).
+    ///
+    /// It is used on teardown, in particular for timers whose callbacks arm them again. Once it
+    /// returns, the timer is not pending and its callback does not run anymore, and
+    /// [`Timer::mod_timer`] does nothing. A concurrent [`Timer::mod_timer`] may still arm the
+    /// timer, but it deletes it again before returning. The callback of this timer may then still
+    /// be running briefly when that [`Timer::mod_timer`] returns, but it does not call
+    /// [`TimerItem::run`].
+    ///
+    /// It may sleep, and must not be called from the callback of the timer.
+    pub fn shutdown_sync(&self) {
+        self.shut_down.store(true, Ordering::SeqCst);
+
+        // The timer is deleted rather than shut down on the C side, since `mod_timer` would then
+        // silently not arm it, which would leak the `Arc` passed to it.
+        self.del_timer_sync();
+    }
+
+    /// Drops the [`Arc`] that the timer owned while it was pending.
+    fn release(&self) {
+        // SAFETY: The timer was pending, so it was armed by `mod_timer` and `Timer` is the field
+        // of an `Arc<T>`.
+        let ptr = unsafe { T::timer_container_of((self as *const Self).cast_mut()) };
+        // SAFETY: The timer is not pending anymore, so it gave back the ownership of the pointer it
+        // got from `Arc::into_raw`.
+        drop(unsafe { Arc::from_raw(ptr) });
+    }
+
+    unsafe extern "C" fn run(ptr: *mut bindings::timer_list) {
+        // `Timer` is `repr(C)` and `timer` is its first field.
+        let timer = ptr.cast::<Self>();
+        // SAFETY: The timer was armed by `mod_timer`, so it is the field of an `Arc<T>`.
+        let data = unsafe { T::timer_container_of(timer) };
+        // SAFETY: The timer is not pending anymore, so we've been given back the ownership of the
+        // pointer it got from `Arc::into_raw`.
+        let this = unsafe { Arc::from_raw(data) };
+
+        // SAFETY: `timer` is valid as long as `this` is.
+        if unsafe { (*timer).shut_down.load(Ordering::SeqCst) } {
+            return;
+        }
+
+        T::run(this)
+    }
+}
+
+/// Declares that a type has a [`Timer<T>`] field.
+///
+/// The intended way of using this trait is via the [`impl_has_timer!`] macro.
+///
+/// # Safety
+///
+/// The [`OFFSET`] constant must be the offset of a field in `Self` of type [`Timer<T>`]. The
+/// methods on this trait must have exactly the behavior that the definitions given below have.
+///
+/// [`impl_has_timer!`]: crate::impl_has_timer
+/// [`OFFSET`]: HasTimer::OFFSET
+pub unsafe trait HasTimer<T> {
+    /// The offset of the [`Timer<T>`] field.
+    const OFFSET: usize;
+
+    /// Returns a pointer to the [`Timer<T>`] field.
+    ///
+    /// # Safety
+    ///
+    /// The provided pointer must point at a valid struct of type `Self`.
+    #[inline]
+    unsafe fn raw_get_timer(ptr: *const Self) -> *const Timer<T> {
+        // SAFETY: The caller promises that the pointer is valid.
+        unsafe { (ptr as *const u8).add(Self::OFFSET) as *const Timer<T> }
+    }
+
+    /// Returns a pointer to the struct containing the [`Timer<T>`] field.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must point at a [`Timer<T>`] field in a struct of type `Self`.
+    #[inline]
+    unsafe fn timer_container_of(ptr: *mut Timer<T>) -> *mut Self
+    where
+        Self: Sized,
+    {
+        // SAFETY: The caller promises that the pointer points at a field of the right type in the
+        // right kind of struct.
+        unsafe { (ptr as *mut u8).sub(Self::OFFSET) as *mut Self }
+    }
+}
+
+/// Used to safely implement the [`HasTimer<T>`] trait.
+///
+/// # Examples
+///
+/// ```
+/// use kernel::time::{impl_has_timer, Timer};
+///
+/// struct MyStruct<T> {
+///     value: T,
+///     timer: Timer<MyStruct<T>>,
+/// }
+///
+/// impl_has_timer! {
+///     impl{T} HasTimer<MyStruct<T>> for MyStruct<T> { self.timer }
+/// }
+/// ```
+#[macro_export]
+macro_rules! impl_has_timer {
+    ($(impl$({$($generics:tt)*})?
+       HasTimer<$timer_type:ty>
+       for $self:ty
+       { self.$field:ident }
+    )*) => {$(
+        // SAFETY: The implementation of `raw_get_timer` only compiles if the field has the right
+        // type.
+        unsafe impl$(<$($generics)+>)? $crate::time::HasTimer<$timer_type> for $self {
+            const OFFSET: usize = ::core::mem::offset_of!(Self, $field) as usize;
+
+            #[inline]
+            unsafe fn raw_get_timer(ptr: *const Self) -> *const $crate::time::Timer<$timer_type> {
+                // SAFETY: The caller promises that the pointer is not dangling.
+                unsafe {
+                    ::core::ptr::addr_of!((*ptr).$field)
+                }
+            }
+        }
+    )*};
+}
+pub use impl_has_timer;
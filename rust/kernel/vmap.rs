@@ -0,0 +1,189 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Contiguous kernel mappings of arrays of pages.
+//!
+//! C header: [`include/linux/vmalloc.h`](srctree/include/linux/vmalloc.h)
+
+use crate::{
+    bindings,
+    error::{code::*, Result},
+    page::{Page, PAGE_SIZE},
+};
+use alloc::vec::Vec;
+use core::{
+    ffi::{c_uint, c_ulong},
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+};
+
+mod private {
+    /// Marker that a trait cannot be implemented outside of this crate
+    pub trait Sealed {}
+}
+
+/// An array of pages that can back a [`VMap`].
+///
+/// It is implemented for `Vec<Page>`, `&[Page]` and `&mut [Page]` only, whose [`Deref`]
+/// implementations keep returning the same pages, so that the pages cannot change under the
+/// mapping.
+pub trait PageArray: Deref<Target = [Page]> + private::Sealed {}
+
+impl private::Sealed for Vec<Page> {}
+impl PageArray for Vec<Page> {}
+
+impl private::Sealed for &[Page] {}
+impl PageArray for &[Page] {}
+
+impl private::Sealed for &mut [Page] {}
+impl PageArray for &mut [Page] {}
+
+/// The protection bits of a kernel mapping.
+///
+/// Only writable protections are provided, since [`VMap`] allows writing through the mapping.
+#[derive(Clone, Copy)]
+pub struct PageProt(bindings::pgprot_t);
+
+impl PageProt {
+    /// Read-write, cacheable memory (`PAGE_KERNEL`).
+    pub fn kernel() -> Self {
+        // SAFETY: FFI call without safety requirements.
+        Self(unsafe { bindings::PAGE_KERNEL() })
+    }
+
+    /// Returns the same protection, but with caching disabled (`pgprot_noncached`).
+    pub fn noncached(self) -> Self {
+        // SAFETY: FFI call without safety requirements.
+        Self(unsafe { bindings::pgprot_noncached(self.0) })
+    }
+}
+
+/// A virtually contiguous kernel mapping of an array of pages.
+///
+/// The pages are given as a [`PageArray`], i.e., a `Vec<Page>` when the mapping takes ownership
+/// of them, or a `&[Page]`/`&mut [Page]` when it borrows them. The pages
+/// are unmapped when the [`VMap`] is dropped, or when they are taken back with
+/// [`VMap::into_inner`].
+///
+/// # Invariants
+///
+/// `addr` is the address returned by `vmap` for the `count` pages in `pages`, and is valid for
+/// `count * PAGE_SIZE` bytes until it is passed to `vunmap`.
+///
+/// # Examples
+///
+/// ```
+/// use kernel::page::{Page, PAGE_SIZE};
+/// use kernel::vmap::{PageProt, VMap};
+///
+/// let mut pages = Vec::new();
+/// for _ in 0..3 {
+///     pages.push(Page::alloc_page(GFP_KERNEL | __GFP_ZERO)?, GFP_KERNEL)?;
+/// }
+///
+/// let mut map = VMap::new(pages, PageProt::kernel())?;
+/// assert_eq!(map.len(), 3 * PAGE_SIZE);
+///
+/// // Write across the boundary between the first and second pages.
+/// map.as_mut_slice()[PAGE_SIZE - 2..PAGE_SIZE + 2].copy_from_slice(&[1, 2, 3, 4]);
+///
+/// let pages = map.into_inner();
+/// pages[0].with_slice(|data| assert_eq!(&data[PAGE_SIZE - 2..], &[1, 2]));
+/// pages[1].with_slice(|data| assert_eq!(&data[..2], &[3, 4]));
+/// pages[2].with_slice(|data| assert!(data.iter().all(|b| *b == 0)));
+/// # Ok::<(), Error>(())
+/// ```
+pub struct VMap<P: PageArray> {
+    addr: NonNull<u8>,
+    count: usize,
+    pages: P,
+}
+
+// SAFETY: The mapping is usable from any thread, and the pages are only accessed through it the
+// same way they would be through `P`.
+unsafe impl<P: PageArray + Send> Send for VMap<P> {}
+
+// SAFETY: Shared references to `VMap` only allow reading from the mapping, which is as safe as
+// sharing `P`.
+unsafe impl<P: PageArray + Sync> Sync for VMap<P> {}
+
+impl<P: PageArray> VMap<P> {
+    /// Maps the given pages into a virtually contiguous kernel mapping with the given protection.
+    ///
+    /// Returns [`EINVAL`] if there are no pages or too many of them, and [`ENOMEM`] if the mapping
+    /// could not be created.
+    pub fn new(pages: P, prot: PageProt) -> Result<Self> {
+        let count = pages.len();
+        let raw_count: c_uint = count.try_into().map_err(|_| EINVAL)?;
+        if count == 0 {
+            return Err(EINVAL);
+        }
+
+        // SAFETY: `Page` is transparent over `*mut bindings::page`, so `pages` is a valid array of
+        // `count` page pointers. `vmap` does not modify the array, and the pages stay alive while
+        // they are mapped since `Self` keeps `pages` until the mapping is removed.
+        let addr = unsafe {
+            bindings::vmap(
+                pages.as_ptr() as *mut *mut bindings::page,
+                raw_count,
+                bindings::VM_MAP as c_ulong,
+                prot.0,
+            )
+        };
+        let addr = NonNull::new(addr.cast::<u8>()).ok_or(ENOMEM)?;
+
+        // INVARIANT: `addr` was just returned by `vmap` for the `count` pages of `pages`.
+        Ok(Self { addr, count, pages })
+    }
+
+    /// Returns the length of the mapping in bytes.
+    pub fn len(&self) -> usize {
+        self.count * PAGE_SIZE
+    }
+
+    /// Returns whether the mapping is empty, which it never is.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Returns the pages that back this mapping.
+    pub fn pages(&self) -> &[Page] {
+        &self.pages
+    }
+
+    /// Returns the contents of the mapping as a byte slice.
+    pub fn as_slice(&self) -> &[u8] {
+        // SAFETY: By the type invariants, `addr` is valid for `self.len()` bytes. Writers either
+        // hold `&mut self`, or hold the pages mutably (which cannot happen while `P` borrows them),
+        // or use the unsafe `Page::*_raw` methods, whose callers guarantee there are no races.
+        unsafe { core::slice::from_raw_parts(self.addr.as_ptr(), self.len()) }
+    }
+
+    /// Returns the contents of the mapping as a mutable byte slice.
+    ///
+    /// It is only available when `P` gives exclusive access to the pages.
+    pub fn as_mut_slice(&mut self) -> &mut [u8]
+    where
+        P: DerefMut,
+    {
+        // SAFETY: By the type invariants, `addr` is valid for `self.len()` bytes. `&mut self` and
+        // `P: DerefMut` guarantee that nothing else accesses the pages concurrently.
+        unsafe { core::slice::from_raw_parts_mut(self.addr.as_ptr(), self.len()) }
+    }
+
+    /// Removes the mapping and returns the pages that backed it.
+    pub fn into_inner(self) -> P {
+        let me = core::mem::ManuallyDrop::new(self);
+        // SAFETY: By the type invariants, `addr` is mapped. It is not used anymore since `me` is
+        // never dropped.
+        unsafe { bindings::vunmap(me.addr.as_ptr().cast()) };
+        // SAFETY: `me.pages` is valid and is read only once, since `me` is never dropped.
+        unsafe { core::ptr::read(&me.pages) }
+    }
+}
+
+impl<P: PageArray> Drop for VMap<P> {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `addr` is mapped. It is not used anymore.
+        unsafe { bindings::vunmap(self.addr.as_ptr().cast()) };
+    }
+}
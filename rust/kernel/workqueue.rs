@@ -25,6 +25,11 @@
 //!  * The [`WorkItem`] trait is implemented for structs that can be enqueued to a workqueue.
 //!  * The [`WorkItemPointer`] trait is implemented for the pointer type that points at a something
 //!    that implements [`WorkItem`].
+//!  * The [`DelayedWork`] struct is the Rust wrapper for the C `delayed_work` type, for work items
+//!    that are enqueued after a delay with [`Queue::enqueue_delayed`].
+//!
+//! Work items are enqueued either on one of the system queues, such as [`system`], or on a queue
+//! owned by the driver, created with [`OwnedQueue::new`].
 //!
 //! ## Example
 //!
@@ -131,8 +136,10 @@
 //! C header: [`include/linux/workqueue.h`](srctree/include/linux/workqueue.h)
 
 use crate::alloc::{AllocError, Flags};
-use crate::{prelude::*, sync::Arc, sync::LockClassKey, types::Opaque};
-use core::marker::PhantomData;
+use crate::{
+    container_of, prelude::*, sync::Arc, sync::LockClassKey, time::Jiffies, types::Opaque,
+};
+use core::{marker::PhantomData, ops::Deref, ptr::NonNull};
 
 /// Creates a [`Work`] initialiser with the given name and a newly-created lock class.
 #[macro_export]
@@ -143,6 +150,19 @@ macro_rules! new_work {
 }
 pub use new_work;
 
+/// Creates a [`DelayedWork`] initialiser with the given name and newly-created lock classes.
+#[macro_export]
+macro_rules! new_delayed_work {
+    ($($name:literal)?) => {
+        $crate::workqueue::DelayedWork::new(
+            $crate::optional_name!($($name)?),
+            $crate::static_lock_class!(),
+            $crate::static_lock_class!(),
+        )
+    };
+}
+pub use new_delayed_work;
+
 /// A kernel work queue.
 ///
 /// Wraps the kernel's C `struct workqueue_struct`.
@@ -203,6 +223,77 @@ impl Queue {
         }
     }
 
+    /// Enqueues a work item after `delay` jiffies.
+    ///
+    /// This may fail if the work item is already enqueued in a workqueue, in which case the delay
+    /// is left unchanged; see [`Queue::mod_delayed_work`] to change it.
+    ///
+    /// The queue must be `'static` because destroying a queue does not wait for the timers of
+    /// delayed work items, which would enqueue them on a freed queue when they expire.
+    ///
+    /// The work item will be submitted using `WORK_CPU_UNBOUND`.
+    pub fn enqueue_delayed<W, const ID: u64>(
+        &'static self,
+        w: W,
+        delay: Jiffies,
+    ) -> W::EnqueueOutput
+    where
+        W: RawDelayedWorkItem<ID> + Send + 'static,
+    {
+        let queue_ptr = self.0.get();
+
+        // SAFETY: We only return `false` if the `work_struct` is already in a workqueue. The other
+        // `__enqueue` requirements are not relevant since `W` is `Send` and static.
+        //
+        // `RawDelayedWorkItem` guarantees that the `work_struct` is the `work` field of a
+        // `delayed_work`. The rest of the reasoning is the same as in `Queue::enqueue`.
+        unsafe {
+            w.__enqueue(move |work_ptr| {
+                bindings::queue_delayed_work_on(
+                    bindings::wq_misc_consts_WORK_CPU_UNBOUND as _,
+                    queue_ptr,
+                    container_of!(work_ptr, bindings::delayed_work, work).cast_mut(),
+                    delay.as_raw(),
+                )
+            })
+        }
+    }
+
+    /// Enqueues a work item after `delay` jiffies, or changes the delay if it is already pending.
+    ///
+    /// If the work item was already pending, the workqueue keeps the pointer it already owns, so
+    /// `w` is given back (for example, in the `Err` variant for [`Arc`]) with the new delay
+    /// applied. A `delay` of zero runs the work item as soon as possible.
+    ///
+    /// Like with [`Queue::enqueue_delayed`], the queue must be `'static`.
+    ///
+    /// The work item will be submitted using `WORK_CPU_UNBOUND`.
+    pub fn mod_delayed_work<W, const ID: u64>(
+        &'static self,
+        w: W,
+        delay: Jiffies,
+    ) -> W::EnqueueOutput
+    where
+        W: RawDelayedWorkItem<ID> + Send + 'static,
+    {
+        let queue_ptr = self.0.get();
+
+        // SAFETY: `mod_delayed_work_on` returns `true` only if the work item was already pending,
+        // that is, if the `work_struct` is already in a workqueue. Otherwise, it was enqueued and
+        // the workqueue takes ownership of the pointer. The rest of the reasoning is the same as in
+        // `Queue::enqueue_delayed`.
+        unsafe {
+            w.__enqueue(move |work_ptr| {
+                !bindings::mod_delayed_work_on(
+                    bindings::wq_misc_consts_WORK_CPU_UNBOUND as _,
+                    queue_ptr,
+                    container_of!(work_ptr, bindings::delayed_work, work).cast_mut(),
+                    delay.as_raw(),
+                )
+            })
+        }
+    }
+
     /// Tries to spawn the given function or closure as a work item.
     ///
     /// This method can fail because it allocates memory to store the work item.
@@ -221,6 +312,149 @@ impl Queue {
     }
 }
 
+/// Flags used to create an [`OwnedQueue`].
+///
+/// They can be combined with the `|` operator.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct QueueFlags(u32);
+
+impl QueueFlags {
+    /// No flags: the work items run on a per-CPU pool of workers, on the CPU that enqueued them.
+    pub const NONE: QueueFlags = QueueFlags(0);
+
+    /// The work items are not bound to any specific CPU (`WQ_UNBOUND`).
+    ///
+    /// It suits long-running or CPU-intensive work items, at the cost of locality.
+    pub const UNBOUND: QueueFlags = QueueFlags(bindings::wq_flags_WQ_UNBOUND as u32);
+
+    /// The queue is frozen during system suspend (`WQ_FREEZABLE`).
+    ///
+    /// Its pending work items are drained when the system suspends, and no new ones run until the
+    /// system resumes.
+    pub const FREEZABLE: QueueFlags = QueueFlags(bindings::wq_flags_WQ_FREEZABLE as u32);
+
+    /// The queue has a rescuer thread, which guarantees forward progress under memory pressure
+    /// (`WQ_MEM_RECLAIM`).
+    ///
+    /// It is required for queues that may be used during memory reclaim, such as in the I/O path
+    /// of block drivers.
+    pub const MEM_RECLAIM: QueueFlags = QueueFlags(bindings::wq_flags_WQ_MEM_RECLAIM as u32);
+
+    /// The work items run on a pool of workers with higher scheduling priority (`WQ_HIGHPRI`).
+    pub const HIGHPRI: QueueFlags = QueueFlags(bindings::wq_flags_WQ_HIGHPRI as u32);
+
+    /// Get the raw representation of these flags.
+    pub(crate) fn as_raw(self) -> u32 {
+        self.0
+    }
+}
+
+impl core::ops::BitOr for QueueFlags {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// A work queue owned by the driver.
+///
+/// Wraps a C `struct workqueue_struct` created with `alloc_workqueue`. It dereferences to
+/// [`Queue`], so work items are enqueued in the same way as on the system queues, and it is
+/// destroyed when dropped, after running all the work items that are still pending.
+///
+/// Delayed work items cannot be enqueued on it (see [`Queue::enqueue_delayed`]).
+///
+/// # Invariants
+///
+/// `queue` is a valid workqueue created with `alloc_workqueue`, which is owned by this instance.
+///
+/// # Examples
+///
+/// ```
+/// use kernel::c_str;
+/// use kernel::sync::{Arc, Atomic};
+/// use kernel::workqueue::{OwnedQueue, QueueFlags};
+///
+/// let wq = OwnedQueue::new(c_str!("my_driver"), QueueFlags::MEM_RECLAIM, 0)?;
+/// let count = Arc::new(Atomic::<i32>::new(0), GFP_KERNEL)?;
+///
+/// for _ in 0..4 {
+///     let count = count.clone();
+///     wq.try_spawn(GFP_KERNEL, move || count.add(1))?;
+/// }
+///
+/// wq.flush();
+/// assert_eq!(count.load(), 4);
+/// # Ok::<(), Error>(())
+/// ```
+pub struct OwnedQueue {
+    queue: NonNull<bindings::workqueue_struct>,
+}
+
+// SAFETY: Accesses to workqueues used by [`OwnedQueue`] are thread-safe, and it can be destroyed
+// from any thread.
+unsafe impl Send for OwnedQueue {}
+// SAFETY: Accesses to workqueues used by [`OwnedQueue`] are thread-safe.
+unsafe impl Sync for OwnedQueue {}
+
+impl OwnedQueue {
+    /// Creates a new work queue.
+    ///
+    /// `max_active` is the maximum number of work items of the queue that can run at the same time
+    /// on each CPU (or overall, for [`QueueFlags::UNBOUND`] queues). Zero selects the default.
+    ///
+    /// Returns [`ENOMEM`] if the queue cannot be allocated.
+    pub fn new(name: &CStr, flags: QueueFlags, max_active: u32) -> Result<Self> {
+        // Values above the limit are clamped by `alloc_workqueue`, with a warning.
+        let max_active = max_active.try_into().unwrap_or(core::ffi::c_int::MAX);
+
+        // SAFETY: `name` is a valid string, which is copied by `alloc_workqueue`.
+        let queue =
+            unsafe { bindings::alloc_workqueue(name.as_char_ptr(), flags.as_raw(), max_active) };
+
+        // INVARIANT: The queue was just created, and is owned by the new instance.
+        Ok(Self {
+            queue: NonNull::new(queue).ok_or(ENOMEM)?,
+        })
+    }
+
+    /// Waits until all the work items that were pending when it was called have finished running.
+    ///
+    /// It may sleep, and must not be called from a work item of this queue.
+    pub fn flush(&self) {
+        // SAFETY: By the type invariants, `queue` is valid.
+        unsafe { bindings::__flush_workqueue(self.queue.as_ptr()) };
+    }
+
+    /// Waits until the queue is empty, including the work items that are enqueued by the work
+    /// items of the queue while it waits.
+    ///
+    /// Only the work items of the queue may enqueue new work items on it until it returns. It may
+    /// sleep, and must not be called from a work item of this queue.
+    pub fn drain(&self) {
+        // SAFETY: By the type invariants, `queue` is valid.
+        unsafe { bindings::drain_workqueue(self.queue.as_ptr()) };
+    }
+}
+
+impl Deref for OwnedQueue {
+    type Target = Queue;
+
+    fn deref(&self) -> &Queue {
+        // SAFETY: By the type invariants, `queue` is valid until `self` is dropped.
+        unsafe { Queue::from_raw(self.queue.as_ptr()) }
+    }
+}
+
+impl Drop for OwnedQueue {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `queue` is valid and owned by `self`. It is drained
+        // before being destroyed, and delayed work items, whose timers could enqueue them later,
+        // cannot be enqueued on it since it is not `'static`.
+        unsafe { bindings::destroy_workqueue(self.queue.as_ptr()) };
+    }
+}
+
 /// A helper type used in [`try_spawn`].
 ///
 /// [`try_spawn`]: Queue::try_spawn
@@ -405,6 +639,192 @@ impl<T: ?Sized, const ID: u64> Work<T, ID> {
     }
 }
 
+impl<T, const ID: u64> Work<T, ID>
+where
+    T: WorkItem<ID, Pointer = Arc<T>>,
+    T: HasWork<T, ID>,
+{
+    /// Cancels the work item if it is pending, and waits for it to finish if it is running.
+    ///
+    /// If the work item was pending, the [`Arc`] owned by the workqueue is dropped. Returns
+    /// whether it was pending.
+    ///
+    /// It may sleep, and must not be called from the work item itself.
+    pub fn cancel_sync(&self) -> bool {
+        // SAFETY: `work` was initialised by the constructor.
+        if !unsafe { bindings::cancel_work_sync(self.work.get()) } {
+            return false;
+        }
+
+        // SAFETY: The work item was pending, so `Work` is the field of an `Arc<T>` whose pointer
+        // was passed to the workqueue by `__enqueue`.
+        let ptr = unsafe { T::work_container_of((self as *const Self).cast_mut()) };
+        // SAFETY: The work item was cancelled before running, so the workqueue gave back the
+        // ownership of the pointer it got from `Arc::into_raw`.
+        drop(unsafe { Arc::from_raw(ptr) });
+        true
+    }
+
+    /// Waits for the work item to finish running, if it is pending or running.
+    ///
+    /// Returns whether it waited. It may sleep, and must not be called from the work item itself.
+    pub fn flush(&self) -> bool {
+        // SAFETY: `work` was initialised by the constructor.
+        unsafe { bindings::flush_work(self.work.get()) }
+    }
+}
+
+/// Links for a work item that is enqueued after a delay.
+///
+/// Wraps the kernel's C `struct delayed_work`, which combines a `work_struct` with a timer that
+/// enqueues it when it expires.
+///
+/// A field of this type is declared with the [`impl_has_delayed_work!`] macro, and the work item
+/// is then enqueued with [`Queue::enqueue_delayed`] or [`Queue::mod_delayed_work`].
+///
+/// # Examples
+///
+/// The following example shows a link monitor that checks the link once per second:
+///
+/// ```
+/// use kernel::sync::Arc;
+/// use kernel::time::{msecs_to_jiffies, Jiffies};
+/// use kernel::workqueue::{self, impl_has_delayed_work, new_delayed_work, DelayedWork, WorkItem};
+///
+/// #[pin_data]
+/// struct LinkMonitor {
+///     #[pin]
+///     work: DelayedWork<LinkMonitor>,
+/// }
+///
+/// impl_has_delayed_work! {
+///     impl HasDelayedWork<Self> for LinkMonitor { self.work }
+/// }
+///
+/// impl WorkItem for LinkMonitor {
+///     type Pointer = Arc<LinkMonitor>;
+///
+///     fn run(this: Arc<LinkMonitor>) {
+///         pr_info!("Checking the link");
+///         let _ = workqueue::system().enqueue_delayed(this, msecs_to_jiffies(1000));
+///     }
+/// }
+///
+/// let monitor = Arc::pin_init(pin_init!(LinkMonitor {
+///     work <- new_delayed_work!("LinkMonitor::work"),
+/// }), GFP_KERNEL)?;
+///
+/// let wq = workqueue::system();
+/// assert!(wq.enqueue_delayed(monitor.clone(), msecs_to_jiffies(1000)).is_ok());
+///
+/// // The work item is already pending, so the `Arc` is given back.
+/// assert!(wq.enqueue_delayed(monitor.clone(), Jiffies::ZERO).is_err());
+/// assert!(wq.mod_delayed_work(monitor.clone(), msecs_to_jiffies(2000)).is_err());
+///
+/// // Cancelling drops the `Arc` owned by the workqueue.
+/// assert!(monitor.work.cancel_sync());
+/// assert!(!monitor.work.cancel_sync());
+/// # Ok::<(), Error>(())
+/// ```
+#[pin_data]
+#[repr(transparent)]
+pub struct DelayedWork<T: ?Sized, const ID: u64 = 0> {
+    #[pin]
+    dwork: Opaque<bindings::delayed_work>,
+    _inner: PhantomData<T>,
+}
+
+// SAFETY: Kernel work items are usable from any thread.
+//
+// We do not need to constrain `T` since the work item does not actually contain a `T`.
+unsafe impl<T: ?Sized, const ID: u64> Send for DelayedWork<T, ID> {}
+// SAFETY: Kernel work items are usable from any thread.
+//
+// We do not need to constrain `T` since the work item does not actually contain a `T`.
+unsafe impl<T: ?Sized, const ID: u64> Sync for DelayedWork<T, ID> {}
+
+impl<T: ?Sized, const ID: u64> DelayedWork<T, ID> {
+    /// Creates a new instance of [`DelayedWork`].
+    #[inline]
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(
+        name: &'static CStr,
+        work_key: &'static LockClassKey,
+        timer_key: &'static LockClassKey,
+    ) -> impl PinInit<Self>
+    where
+        T: WorkItem<ID>,
+    {
+        pin_init!(Self {
+            dwork <- Opaque::ffi_init(|slot| {
+                // SAFETY: The `WorkItemPointer` implementation promises that `run` can be used as
+                // the work item function.
+                unsafe {
+                    bindings::init_delayed_work_with_key(
+                        slot,
+                        Some(T::Pointer::run),
+                        name.as_char_ptr(),
+                        work_key.as_ptr(),
+                        timer_key.as_ptr(),
+                    )
+                }
+            }),
+            _inner: PhantomData,
+        })
+    }
+
+    /// Get a pointer to the inner `delayed_work`.
+    ///
+    /// # Safety
+    ///
+    /// The provided pointer must not be dangling and must be properly aligned. (But the memory
+    /// need not be initialized.)
+    #[inline]
+    pub unsafe fn raw_get(ptr: *const Self) -> *mut bindings::delayed_work {
+        // SAFETY: The caller promises that the pointer is aligned and not dangling.
+        unsafe { Opaque::raw_get(core::ptr::addr_of!((*ptr).dwork)) }
+    }
+}
+
+impl<T, const ID: u64> DelayedWork<T, ID>
+where
+    T: WorkItem<ID, Pointer = Arc<T>>,
+    T: HasDelayedWork<T, ID>,
+{
+    /// Cancels the work item if it is pending, and waits for it to finish if it is running.
+    ///
+    /// If the work item was pending (including if its timer had not expired yet), the [`Arc`]
+    /// owned by the workqueue is dropped. Returns whether it was pending. A work item that
+    /// enqueues itself again when it runs is cancelled as well.
+    ///
+    /// It may sleep, and must not be called from the work item itself.
+    pub fn cancel_sync(&self) -> bool {
+        // SAFETY: `dwork` was initialised by the constructor.
+        if !unsafe { bindings::cancel_delayed_work_sync(self.dwork.get()) } {
+            return false;
+        }
+
+        // The `work_struct` is the first field of `delayed_work`, and both `Work` and
+        // `DelayedWork` are transparent, so this is the pointer that `HasWork` uses.
+        let work = (self as *const Self).cast_mut().cast::<Work<T, ID>>();
+        // SAFETY: The work item was pending, so `DelayedWork` is the field of an `Arc<T>` whose
+        // pointer was passed to the workqueue by `__enqueue`.
+        let ptr = unsafe { T::work_container_of(work) };
+        // SAFETY: The work item was cancelled before running, so the workqueue gave back the
+        // ownership of the pointer it got from `Arc::into_raw`.
+        drop(unsafe { Arc::from_raw(ptr) });
+        true
+    }
+
+    /// Runs the work item immediately if its timer is pending, and waits for it to finish.
+    ///
+    /// Returns whether it waited. It may sleep, and must not be called from the work item itself.
+    pub fn flush(&self) -> bool {
+        // SAFETY: `dwork` was initialised by the constructor.
+        unsafe { bindings::flush_delayed_work(self.dwork.get()) }
+    }
+}
+
 /// Declares that a type has a [`Work<T, ID>`] field.
 ///
 /// The intended way of using this trait is via the [`impl_has_work!`] macro. You can use the macro
@@ -516,6 +936,65 @@ macro_rules! impl_has_work {
 }
 pub use impl_has_work;
 
+/// Declares that a type has a [`DelayedWork<T, ID>`] field.
+///
+/// The [`HasWork<T, ID>`] supertrait gives access to the `work_struct` inside of the delayed work
+/// item. The intended way of using this trait is via the [`impl_has_delayed_work!`] macro, which
+/// implements both traits.
+///
+/// # Safety
+///
+/// The [`HasWork::OFFSET`] constant must be the offset of a field in `Self` of type
+/// [`DelayedWork<T, ID>`].
+///
+/// [`impl_has_delayed_work!`]: crate::impl_has_delayed_work
+pub unsafe trait HasDelayedWork<T, const ID: u64 = 0>: HasWork<T, ID> {}
+
+/// Used to safely implement the [`HasDelayedWork<T, ID>`] trait.
+///
+/// # Examples
+///
+/// ```
+/// use kernel::workqueue::{impl_has_delayed_work, DelayedWork};
+///
+/// struct MyStruct {
+///     work_field: DelayedWork<MyStruct, 3>,
+/// }
+///
+/// impl_has_delayed_work! {
+///     impl HasDelayedWork<MyStruct, 3> for MyStruct { self.work_field }
+/// }
+/// ```
+#[macro_export]
+macro_rules! impl_has_delayed_work {
+    ($(impl$({$($generics:tt)*})?
+       HasDelayedWork<$work_type:ty $(, $id:tt)?>
+       for $self:ty
+       { self.$field:ident }
+    )*) => {$(
+        // SAFETY: The implementation of `raw_get_work` only compiles if the field has the right
+        // type. The `work_struct` is the first field of `delayed_work`, and both `Work` and
+        // `DelayedWork` are transparent, so the field is also a `Work<T, ID>`.
+        unsafe impl$(<$($generics)+>)? $crate::workqueue::HasWork<$work_type $(, $id)?> for $self {
+            const OFFSET: usize = ::core::mem::offset_of!(Self, $field) as usize;
+
+            #[inline]
+            unsafe fn raw_get_work(ptr: *mut Self) -> *mut $crate::workqueue::Work<$work_type $(, $id)?> {
+                // SAFETY: The caller promises that the pointer is not dangling.
+                let ptr: *mut $crate::workqueue::DelayedWork<$work_type $(, $id)?> = unsafe {
+                    ::core::ptr::addr_of_mut!((*ptr).$field)
+                };
+                ptr.cast()
+            }
+        }
+
+        // SAFETY: `OFFSET` is the offset of the `DelayedWork` field, see above.
+        unsafe impl$(<$($generics)+>)? $crate::workqueue::HasDelayedWork<$work_type $(, $id)?>
+            for $self {}
+    )*};
+}
+pub use impl_has_delayed_work;
+
 impl_has_work! {
     impl{T} HasWork<Self> for ClosureWork<T> { self.work }
 }
@@ -613,6 +1092,35 @@ where
     }
 }
 
+/// A raw work item whose `work_struct` is part of a `delayed_work`.
+///
+/// # Safety
+///
+/// Implementers must ensure that the `work_struct` pointers passed to the `queue_work_on` closure
+/// by [`__enqueue`] point at the `work` field of a `delayed_work` that was initialised with a timer
+/// that enqueues it (for example, by [`DelayedWork::new`]).
+///
+/// [`__enqueue`]: RawWorkItem::__enqueue
+pub unsafe trait RawDelayedWorkItem<const ID: u64>: RawWorkItem<ID> {}
+
+// SAFETY: `HasDelayedWork` guarantees that the `Work` used by `__enqueue` is inside a
+// `DelayedWork`.
+unsafe impl<T, const ID: u64> RawDelayedWorkItem<ID> for Arc<T>
+where
+    T: WorkItem<ID, Pointer = Self>,
+    T: HasDelayedWork<T, ID>,
+{
+}
+
+// SAFETY: `HasDelayedWork` guarantees that the `Work` used by `__enqueue` is inside a
+// `DelayedWork`.
+unsafe impl<T, const ID: u64> RawDelayedWorkItem<ID> for Pin<Box<T>>
+where
+    T: WorkItem<ID, Pointer = Self>,
+    T: HasDelayedWork<T, ID>,
+{
+}
+
 /// Returns the system work queue (`system_wq`).
 ///
 /// It is the one used by `schedule[_delayed]_work[_on]()`. Multi-CPU multi-threaded. There are
@@ -0,0 +1,588 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! XArray, an efficient map from indices to pointers.
+//!
+//! C header: [`include/linux/xarray.h`](srctree/include/linux/xarray.h)
+//!
+//! Reference: <https://docs.kernel.org/core-api/xarray.html>
+
+use crate::{
+    alloc::Flags,
+    bindings,
+    error::{code::*, to_result, Error, Result},
+    init::PinInit,
+    pin_init,
+    types::{ForeignOwnable, Opaque},
+};
+use core::{
+    ffi::{c_ulong, c_void},
+    marker::PhantomData,
+    mem,
+    ops::{Bound, RangeBounds},
+    pin::Pin,
+    ptr,
+};
+use macros::{pin_data, pinned_drop};
+
+/// The kind of IDs that an [`XArray`] allocates with [`XArray::alloc`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AllocKind {
+    /// IDs start at 0 (`XA_FLAGS_ALLOC`).
+    Alloc,
+    /// IDs start at 1, which leaves 0 free to mean "no ID" (`XA_FLAGS_ALLOC1`).
+    Alloc1,
+}
+
+/// A mark, which can be set on the entries of an [`XArray`] to find them quickly.
+///
+/// Mark 0 is used to track free entries for [`XArray::alloc`], so it is not available.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mark {
+    /// `XA_MARK_1`.
+    One,
+    /// `XA_MARK_2`.
+    Two,
+}
+
+impl Mark {
+    fn as_raw(self) -> bindings::xa_mark_t {
+        match self {
+            Self::One => bindings::XA_MARK_1,
+            Self::Two => bindings::XA_MARK_2,
+        }
+    }
+}
+
+/// The error returned when a value cannot be stored in an [`XArray`].
+///
+/// It gives the value back to the caller.
+pub struct StoreError<T> {
+    /// The error that occurred, usually [`ENOMEM`], or [`EBUSY`] for allocations.
+    pub error: Error,
+    /// The value that was not stored.
+    pub value: T,
+}
+
+impl<T> From<StoreError<T>> for Error {
+    fn from(e: StoreError<T>) -> Self {
+        e.error
+    }
+}
+
+/// A map from indices to values, with support for allocating indices.
+///
+/// Wraps the kernel's C `struct xarray`. Values are stored as the pointers returned by
+/// [`ForeignOwnable::into_foreign`], so they are owned by the array, and they are accessed as
+/// [`ForeignOwnable::Borrowed`] through a [`Guard`] returned by [`XArray::lock`].
+///
+/// Instances of [`XArray`] need to be pinned. The recommended way to create such instances is with
+/// the [`pin_init`](crate::pin_init) macro, or with [`Box::pin_init`].
+///
+/// # Invariants
+///
+/// `xa` is an initialised `struct xarray` with the flags of an [`AllocKind`]. Its entries are
+/// either null, reserved, or pointers returned by [`ForeignOwnable::into_foreign`] that are owned
+/// by the array.
+///
+/// # Examples
+///
+/// The following example shows an ID table:
+///
+/// ```
+/// use kernel::sync::Arc;
+/// use kernel::xarray::{AllocKind, Mark, XArray};
+///
+/// struct Node {
+///     value: u32,
+/// }
+///
+/// let xa = Box::pin_init(XArray::<Arc<Node>>::new(AllocKind::Alloc1), GFP_KERNEL)?;
+///
+/// let a = xa.alloc(.., Arc::new(Node { value: 10 }, GFP_KERNEL)?, GFP_KERNEL)?;
+/// let b = xa.alloc(.., Arc::new(Node { value: 20 }, GFP_KERNEL)?, GFP_KERNEL)?;
+/// assert_eq!((a, b), (1, 2));
+///
+/// // An ID can also be chosen, and reserved until the value is ready.
+/// let reservation = xa.reserve(10, GFP_KERNEL)?;
+/// assert!(xa.alloc(10..=10, Arc::new(Node { value: 0 }, GFP_KERNEL)?, GFP_KERNEL).is_err());
+/// reservation.fill(Arc::new(Node { value: 30 }, GFP_KERNEL)?, GFP_KERNEL)?;
+///
+/// {
+///     let guard = xa.lock();
+///     assert_eq!(guard.get(2).map(|node| node.value), Some(20));
+///     assert!(guard.get(3).is_none());
+///
+///     guard.set_mark(10, Mark::One);
+///     assert!(guard.get_mark(10, Mark::One));
+///
+///     let mut sum = 0;
+///     for (index, node) in guard.iter(2..) {
+///         assert!(index == 2 || index == 10);
+///         sum += node.value;
+///     }
+///     assert_eq!(sum, 50);
+///
+///     let marked = guard.iter_marked(.., Mark::One).map(|(index, _)| index);
+///     assert!(marked.eq([10]));
+/// }
+///
+/// let node = xa.erase(1).ok_or(ENOENT)?;
+/// assert_eq!(node.value, 10);
+/// assert!(xa.erase(1).is_none());
+/// # Ok::<(), Error>(())
+/// ```
+#[pin_data(PinnedDrop)]
+pub struct XArray<T: ForeignOwnable> {
+    #[pin]
+    xa: Opaque<bindings::xarray>,
+    _p: PhantomData<T>,
+}
+
+// SAFETY: The array owns its values, which can be dropped from any thread if `T` is `Send`.
+unsafe impl<T: ForeignOwnable + Send> Send for XArray<T> {}
+
+// SAFETY: Shared references to the array allow values to be moved in and out of it from any
+// thread, which requires `T` to be `Send`, and to be borrowed from any thread, which requires `T`
+// to be `Sync`. Accesses to the array itself are synchronised by its lock.
+unsafe impl<T: ForeignOwnable + Send + Sync> Sync for XArray<T> {}
+
+impl<T: ForeignOwnable> XArray<T> {
+    /// Creates a new, empty [`XArray`] that allocates IDs of the given kind.
+    pub fn new(kind: AllocKind) -> impl PinInit<Self> {
+        let flags = match kind {
+            AllocKind::Alloc => bindings::XA_FLAGS_ALLOC,
+            AllocKind::Alloc1 => bindings::XA_FLAGS_ALLOC1,
+        };
+        pin_init!(Self {
+            // SAFETY: `slot` is valid while the closure is called.
+            xa <- Opaque::ffi_init(|slot| unsafe { bindings::xa_init_flags(slot, flags) }),
+            _p: PhantomData,
+        })
+    }
+
+    /// Locks the array (`xa_lock`).
+    ///
+    /// The returned guard gives access to the values, and allows several changes to be made
+    /// atomically. The other methods of [`XArray`] take the lock themselves, so they must not be
+    /// called while the guard exists.
+    pub fn lock(&self) -> Guard<'_, T> {
+        // SAFETY: By the type invariants, `xa` is initialised.
+        unsafe { bindings::xa_lock(self.xa.get()) };
+        // INVARIANT: The lock was just acquired above.
+        Guard {
+            xa: self,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Stores `value` at `index` (`xa_store`).
+    ///
+    /// Returns the value that was previously stored at `index`, if any. A reservation of `index`
+    /// is filled. On failure, `value` is given back.
+    pub fn store(&self, index: usize, value: T, gfp: Flags) -> Result<Option<T>, StoreError<T>> {
+        self.lock().store(index, value, gfp)
+    }
+
+    /// Removes and returns the value at `index` (`xa_erase`).
+    ///
+    /// A reservation of `index` is released.
+    pub fn erase(&self, index: usize) -> Option<T> {
+        self.lock().erase(index)
+    }
+
+    /// Stores `value` at a free index in `limit`, and returns that index (`xa_alloc`).
+    ///
+    /// Returns [`EBUSY`] if there is no free index in `limit`. On failure, `value` is given back.
+    pub fn alloc(
+        &self,
+        limit: impl RangeBounds<u32>,
+        value: T,
+        gfp: Flags,
+    ) -> Result<u32, StoreError<T>> {
+        self.lock().alloc(limit, value, gfp)
+    }
+
+    /// Reserves `index`, so that it is not allocated by [`XArray::alloc`] (`xa_insert`).
+    ///
+    /// Memory is allocated for the entry, so that filling the reservation with
+    /// [`Reservation::fill`] does not need to allocate, unless the reservation was released with
+    /// [`XArray::erase`] in the meantime. Returns [`EBUSY`] if `index` is in use or reserved.
+    pub fn reserve(&self, index: usize, gfp: Flags) -> Result<Reservation<'_, T>> {
+        {
+            let guard = self.lock();
+            // SAFETY: By the type invariants of `Guard`, the lock is held. Inserting a null entry
+            // reserves the index.
+            to_result(unsafe {
+                bindings::__xa_insert(
+                    guard.as_ptr(),
+                    index as c_ulong,
+                    ptr::null_mut(),
+                    gfp.as_raw(),
+                )
+            })?;
+        }
+
+        // INVARIANT: `index` was reserved above.
+        Ok(Reservation { xa: self, index })
+    }
+}
+
+#[pinned_drop]
+impl<T: ForeignOwnable> PinnedDrop for XArray<T> {
+    fn drop(self: Pin<&mut Self>) {
+        let xa = self.xa.get();
+        let mut index: c_ulong = 0;
+
+        // SAFETY: By the type invariants, `xa` is initialised. No one else can access it, since
+        // we have exclusive access to the array.
+        let mut entry =
+            unsafe { bindings::xa_find(xa, &mut index, c_ulong::MAX, bindings::XA_PRESENT) };
+        while !entry.is_null() {
+            // SAFETY: By the type invariants, the entries found are owned by the array, and they
+            // cannot be borrowed anymore.
+            drop(unsafe { T::from_foreign(entry) });
+            // SAFETY: As above.
+            entry = unsafe {
+                bindings::xa_find_after(xa, &mut index, c_ulong::MAX, bindings::XA_PRESENT)
+            };
+        }
+
+        // SAFETY: As above. The entries were all dropped, so this only frees the nodes.
+        unsafe { bindings::xa_destroy(xa) };
+    }
+}
+
+/// A guard that holds the lock of an [`XArray`].
+///
+/// It is returned by [`XArray::lock`], and releases the lock when dropped. It is not [`Send`]
+/// because the lock must be released by the thread that acquired it.
+///
+/// # Invariants
+///
+/// The lock of `xa` is held while the guard exists.
+pub struct Guard<'a, T: ForeignOwnable> {
+    xa: &'a XArray<T>,
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl<'a, T: ForeignOwnable> Guard<'a, T> {
+    fn as_ptr(&self) -> *mut bindings::xarray {
+        self.xa.xa.get()
+    }
+
+    /// Returns a borrow of the value at `index`, if any (`xa_load`).
+    pub fn get(&self, index: usize) -> Option<T::Borrowed<'_>> {
+        // SAFETY: By the type invariants of `XArray`, `xa` is initialised.
+        let entry = unsafe { bindings::xa_load(self.as_ptr(), index as c_ulong) };
+        if entry.is_null() {
+            return None;
+        }
+
+        // SAFETY: By the type invariants of `XArray`, the entry comes from `into_foreign`. The
+        // lock is held, so it can only be removed through `&mut self`, after the borrow ends.
+        Some(unsafe { T::borrow(entry) })
+    }
+
+    /// Stores `value` at `index` (`__xa_store`).
+    ///
+    /// See [`XArray::store`]. The lock may be released while memory is allocated.
+    pub fn store(
+        &mut self,
+        index: usize,
+        value: T,
+        gfp: Flags,
+    ) -> Result<Option<T>, StoreError<T>> {
+        let new = into_entry(value)?;
+
+        // SAFETY: By the type invariants, the lock is held, and `new` comes from `into_foreign`.
+        let old =
+            unsafe { bindings::__xa_store(self.as_ptr(), index as c_ulong, new, gfp.as_raw()) };
+
+        // SAFETY: `xa_err` may be called with any entry.
+        if let Err(error) = to_result(unsafe { bindings::xa_err(old) }) {
+            // SAFETY: `new` was not stored, so we still own it.
+            let value = unsafe { T::from_foreign(new) };
+            return Err(StoreError { error, value });
+        }
+
+        // SAFETY: `old` was removed from the array, and it can't be borrowed anymore, since we
+        // have a mutable reference to the guard.
+        Ok(unsafe { T::try_from_foreign(old) })
+    }
+
+    /// Removes and returns the value at `index` (`__xa_erase`).
+    ///
+    /// See [`XArray::erase`].
+    pub fn erase(&mut self, index: usize) -> Option<T> {
+        // SAFETY: By the type invariants, the lock is held.
+        let old = unsafe { bindings::__xa_erase(self.as_ptr(), index as c_ulong) };
+
+        // SAFETY: `old` was removed from the array, and it can't be borrowed anymore, since we
+        // have a mutable reference to the guard.
+        unsafe { T::try_from_foreign(old) }
+    }
+
+    /// Stores `value` at a free index in `limit`, and returns that index (`__xa_alloc`).
+    ///
+    /// See [`XArray::alloc`]. The lock may be released while memory is allocated.
+    pub fn alloc(
+        &mut self,
+        limit: impl RangeBounds<u32>,
+        value: T,
+        gfp: Flags,
+    ) -> Result<u32, StoreError<T>> {
+        let new = into_entry(value)?;
+
+        let min = match limit.start_bound() {
+            Bound::Included(&min) => Some(min),
+            Bound::Excluded(&min) => min.checked_add(1),
+            Bound::Unbounded => Some(0),
+        };
+        let max = match limit.end_bound() {
+            Bound::Included(&max) => Some(max),
+            Bound::Excluded(&max) => max.checked_sub(1),
+            Bound::Unbounded => Some(u32::MAX),
+        };
+
+        let mut id = 0;
+        let ret = match (min, max) {
+            (Some(min), Some(max)) if min <= max => {
+                // SAFETY: By the type invariants, the lock is held, and `new` comes from
+                // `into_foreign`.
+                unsafe {
+                    bindings::__xa_alloc(
+                        self.as_ptr(),
+                        &mut id,
+                        new,
+                        bindings::xa_limit { min, max },
+                        gfp.as_raw(),
+                    )
+                }
+            }
+            _ => EBUSY.to_errno(),
+        };
+
+        if let Err(error) = to_result(ret) {
+            // SAFETY: `new` was not stored, so we still own it.
+            let value = unsafe { T::from_foreign(new) };
+            return Err(StoreError { error, value });
+        }
+        Ok(id)
+    }
+
+    /// Sets `mark` on the entry at `index`, if there is one (`__xa_set_mark`).
+    pub fn set_mark(&self, index: usize, mark: Mark) {
+        // SAFETY: By the type invariants, the lock is held.
+        unsafe { bindings::__xa_set_mark(self.as_ptr(), index as c_ulong, mark.as_raw()) };
+    }
+
+    /// Clears `mark` on the entry at `index` (`__xa_clear_mark`).
+    pub fn clear_mark(&self, index: usize, mark: Mark) {
+        // SAFETY: By the type invariants, the lock is held.
+        unsafe { bindings::__xa_clear_mark(self.as_ptr(), index as c_ulong, mark.as_raw()) };
+    }
+
+    /// Returns whether `mark` is set on the entry at `index` (`xa_get_mark`).
+    pub fn get_mark(&self, index: usize, mark: Mark) -> bool {
+        // SAFETY: By the type invariants of `XArray`, `xa` is initialised.
+        unsafe { bindings::xa_get_mark(self.as_ptr(), index as c_ulong, mark.as_raw()) }
+    }
+
+    /// Returns an iterator over the values with an index in `range`, in order (`xa_find`).
+    pub fn iter(&self, range: impl RangeBounds<usize>) -> Iter<'_, T> {
+        Iter::new(self, range, bindings::XA_PRESENT)
+    }
+
+    /// Returns an iterator over the values with an index in `range` and with `mark` set, in order.
+    pub fn iter_marked(&self, range: impl RangeBounds<usize>, mark: Mark) -> Iter<'_, T> {
+        Iter::new(self, range, mark.as_raw())
+    }
+}
+
+impl<T: ForeignOwnable> Drop for Guard<'_, T> {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, the lock is held.
+        unsafe { bindings::xa_unlock(self.as_ptr()) };
+    }
+}
+
+/// An iterator over the values of an [`XArray`], which yields their indices and borrows.
+///
+/// It is returned by [`Guard::iter`] and [`Guard::iter_marked`].
+pub struct Iter<'a, T: ForeignOwnable> {
+    guard: &'a Guard<'a, T>,
+    index: c_ulong,
+    max: c_ulong,
+    filter: bindings::xa_mark_t,
+    started: bool,
+}
+
+impl<'a, T: ForeignOwnable> Iter<'a, T> {
+    fn new(
+        guard: &'a Guard<'a, T>,
+        range: impl RangeBounds<usize>,
+        filter: bindings::xa_mark_t,
+    ) -> Self {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => Some(start),
+            Bound::Excluded(&start) => start.checked_add(1),
+            Bound::Unbounded => Some(0),
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => Some(end),
+            Bound::Excluded(&end) => end.checked_sub(1),
+            Bound::Unbounded => Some(usize::MAX),
+        };
+
+        match (start, end) {
+            (Some(index), Some(max)) if index <= max => Self {
+                guard,
+                index: index as c_ulong,
+                max: max as c_ulong,
+                filter,
+                started: false,
+            },
+            // An empty range: finding after the maximum index never finds anything.
+            _ => Self {
+                guard,
+                index: c_ulong::MAX,
+                max: c_ulong::MAX,
+                filter,
+                started: true,
+            },
+        }
+    }
+}
+
+impl<'a, T: ForeignOwnable> Iterator for Iter<'a, T> {
+    type Item = (usize, T::Borrowed<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let xa = self.guard.as_ptr();
+        let entry = if self.started {
+            // SAFETY: By the type invariants of `XArray`, `xa` is initialised.
+            unsafe { bindings::xa_find_after(xa, &mut self.index, self.max, self.filter) }
+        } else {
+            self.started = true;
+            // SAFETY: By the type invariants of `XArray`, `xa` is initialised.
+            unsafe { bindings::xa_find(xa, &mut self.index, self.max, self.filter) }
+        };
+        if entry.is_null() {
+            return None;
+        }
+
+        // SAFETY: By the type invariants of `XArray`, the entry comes from `into_foreign`. The
+        // lock is held, so it can only be removed through a mutable reference to the guard, after
+        // the borrow ends.
+        Some((self.index as usize, unsafe { T::borrow(entry) }))
+    }
+}
+
+/// A reserved index of an [`XArray`].
+///
+/// It is returned by [`XArray::reserve`]. The reservation is released when it is dropped, which
+/// takes the lock of the array, so it must not be dropped while a [`Guard`] exists.
+///
+/// # Invariants
+///
+/// `index` was reserved in `xa`.
+pub struct Reservation<'a, T: ForeignOwnable> {
+    xa: &'a XArray<T>,
+    index: usize,
+}
+
+impl<T: ForeignOwnable> Reservation<'_, T> {
+    /// Returns the reserved index.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Stores `value` at the reserved index (`__xa_cmpxchg`).
+    ///
+    /// Returns [`EBUSY`] if the reservation was released with [`XArray::erase`] in the meantime.
+    /// On failure, `value` is given back, and the reservation is released.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kernel::xarray::{AllocKind, XArray};
+    ///
+    /// let xa = Box::pin_init(XArray::<Box<u32>>::new(AllocKind::Alloc), GFP_KERNEL)?;
+    ///
+    /// let reservation = xa.reserve(3, GFP_KERNEL)?;
+    /// assert!(xa.lock().get(3).is_none());
+    /// reservation.fill(Box::new(30, GFP_KERNEL)?, GFP_KERNEL)?;
+    /// assert_eq!(xa.lock().get(3).copied(), Some(30));
+    ///
+    /// // The reservation was released and the index reused, so the value is given back.
+    /// let reservation = xa.reserve(4, GFP_KERNEL)?;
+    /// assert!(xa.erase(4).is_none());
+    /// xa.store(4, Box::new(40, GFP_KERNEL)?, GFP_KERNEL)?;
+    /// let err = reservation.fill(Box::new(41, GFP_KERNEL)?, GFP_KERNEL).unwrap_err();
+    /// assert_eq!(err.error, EBUSY);
+    /// assert_eq!(*err.value, 41);
+    /// assert_eq!(xa.lock().get(4).copied(), Some(40));
+    /// # Ok::<(), Error>(())
+    /// ```
+    pub fn fill(self, value: T, gfp: Flags) -> Result<(), StoreError<T>> {
+        let new = into_entry(value)?;
+
+        let result = {
+            let guard = self.xa.lock();
+            let xa = guard.as_ptr();
+            let index = self.index as c_ulong;
+            // SAFETY: By the type invariants of `Guard`, the lock is held, and `new` comes from
+            // `into_foreign`. A reserved entry is stored as `XA_ZERO_ENTRY`, so it is replaced,
+            // while an empty slot or any value stored by someone else is left in place.
+            let old = unsafe {
+                bindings::__xa_cmpxchg(xa, index, bindings::xa_zero_entry(), new, gfp.as_raw())
+            };
+            // SAFETY: `xa_err` may be called with any entry.
+            to_result(unsafe { bindings::xa_err(old) }).and_then(|()| {
+                // `__xa_cmpxchg` returns a replaced reserved entry as null, like an empty slot
+                // that was left alone. The lock is held, so check whether `new` was stored instead.
+                // SAFETY: By the type invariants of `XArray`, `xa` is initialised.
+                if unsafe { bindings::xa_load(xa, index) } == new {
+                    Ok(())
+                } else {
+                    Err(EBUSY)
+                }
+            })
+        };
+
+        if let Err(error) = result {
+            // SAFETY: `new` was not stored, so we still own it.
+            let value = unsafe { T::from_foreign(new) };
+            return Err(StoreError { error, value });
+        }
+
+        // The reservation was filled, so there is nothing to release.
+        mem::forget(self);
+        Ok(())
+    }
+}
+
+impl<T: ForeignOwnable> Drop for Reservation<'_, T> {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants of `XArray`, `xa` is initialised. `xa_release` only
+        // removes the entry if it is still reserved.
+        unsafe { bindings::xa_release(self.xa.xa.get(), self.index as c_ulong) };
+    }
+}
+
+/// Converts `value` into an entry that can be stored in an [`XArray`].
+///
+/// Pointers whose two low bits are `0b10` are internal entries of the array, so they are rejected
+/// with [`EINVAL`]. Allocations are always aligned enough, so it can only happen for the dangling
+/// pointers of zero-sized types.
+fn into_entry<T: ForeignOwnable>(value: T) -> Result<*mut c_void, StoreError<T>> {
+    let entry = value.into_foreign().cast_mut();
+    if (entry as usize) & 3 == 2 {
+        return Err(StoreError {
+            error: EINVAL,
+            // SAFETY: `entry` was just returned by `into_foreign`.
+            value: unsafe { T::from_foreign(entry) },
+        });
+    }
+    Ok(entry)
+}